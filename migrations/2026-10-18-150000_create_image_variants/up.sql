@@ -0,0 +1,12 @@
+CREATE TABLE image_variants (
+    id SERIAL PRIMARY KEY,
+    image_id INTEGER NOT NULL REFERENCES images(id) ON DELETE CASCADE,
+    name VARCHAR(32) NOT NULL,
+    format VARCHAR(16) NOT NULL,
+    image_url TEXT NOT NULL,
+    image_key TEXT NOT NULL,
+    width INT NOT NULL,
+    height INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (image_id, name, format)
+);
@@ -0,0 +1 @@
+ALTER TABLE images ADD COLUMN in_library BOOLEAN NOT NULL DEFAULT false;
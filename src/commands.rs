@@ -0,0 +1,87 @@
+use diesel_async::{AsyncConnection, AsyncPgConnection};
+
+use chrono::Duration;
+
+use crate::image_gc::{collect_garbage, parse_hours, GcOptions};
+use crate::models::user::{RegisterUser, UserRole, UserType};
+use crate::repositories::user::UserRepository;
+use crate::utils::storage::storage_from_env;
+
+const USAGE: &str = "Usage:
+  zive-teplice-backend-rust create-admin <username> <email> <password>
+  zive-teplice-backend-rust gc-images [--dry-run] [--grace-hours <hours>]";
+
+async fn load_db_connection() -> Result<AsyncPgConnection, String> {
+    let database_url =
+        std::env::var("DATABASE_URL").map_err(|e| format!("DATABASE_URL not set: {}", e))?;
+
+    AsyncPgConnection::establish(&database_url)
+        .await
+        .map_err(|e| format!("Cannot connect to Postgres: {}", e))
+}
+
+/// Runs a management command given on the command line instead of starting the server.
+pub async fn run(args: &[String]) -> Result<(), String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["create-admin", username, email, password] => {
+            create_admin(username, email, password).await
+        }
+        ["gc-images", options @ ..] => gc_images(options).await,
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+async fn create_admin(username: &str, email: &str, password: &str) -> Result<(), String> {
+    let mut c = load_db_connection().await?;
+
+    let registration = RegisterUser {
+        username: username.to_string(),
+        email: email.to_string(),
+        password: password.to_string(),
+        phone_number: String::new(),
+        description: None,
+        user_type: UserType::Seller,
+    };
+
+    let user = UserRepository::create(&mut c, registration, UserRole::Admin)
+        .await
+        .map_err(|e| format!("Cannot create admin: {}", e))?;
+    let user = UserRepository::mark_email_verified(&mut c, user.id)
+        .await
+        .map_err(|e| format!("Cannot verify admin email: {}", e))?;
+
+    println!(
+        "Admin created: {} <{}> (id {})",
+        user.username, user.email, user.id
+    );
+    Ok(())
+}
+
+async fn gc_images(args: &[&str]) -> Result<(), String> {
+    let mut options = GcOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--dry-run" => options.dry_run = true,
+            "--grace-hours" => {
+                let hours = args.next().ok_or_else(|| USAGE.to_string())?;
+                options.grace_period = Duration::hours(parse_hours(hours)?);
+            }
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    let mut c = load_db_connection().await?;
+    let storage = storage_from_env().await?;
+
+    let report = collect_garbage(&mut c, &storage, &options).await?;
+    print!("{}", report);
+
+    if report.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} error(s) while deleting", report.errors.len()))
+    }
+}
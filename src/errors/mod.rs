@@ -1,19 +1,288 @@
-use ::std::error::Error;
+use std::fmt;
+
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
 use rocket::http::Status;
 use rocket::response::status::Custom;
+use rocket::response::{self, Responder, Response};
 use rocket::serde::json::{json, Value};
+use rocket::Request;
+
+use crate::repositories::image::{BatchUploadError, ImageError};
+use crate::utils::validation::FieldError;
+
+/// Every error a route can return. Responds with the matching status and a JSON body
+/// `{code, message, details}`.
+#[derive(Debug)]
+pub enum ApiError {
+    /// 400: the request is malformed or fails validation.
+    Validation(String),
+    /// 401
+    Unauthorized(String),
+    /// 403
+    Forbidden(String),
+    /// 404
+    NotFound(String),
+    /// 409: a unique constraint or a state check refused the change.
+    Conflict(String),
+    /// 422: the request references a row that does not exist.
+    InvalidReference(String),
+    /// 422: one or more fields break their validation rules.
+    InvalidFields(Vec<FieldError>),
+    /// 429, with the number of seconds to wait in `Retry-After`.
+    TooManyRequests { retry_after: u64 },
+    /// 500: logged, the client only sees a generic message.
+    Internal(String),
+    /// 501: the configured backend cannot do what was asked.
+    NotImplemented(String),
+    /// 502: the object storage failed. Logged like `Internal`.
+    Storage(String),
+}
+
+impl ApiError {
+    pub fn internal(e: impl fmt::Display) -> Self {
+        ApiError::Internal(e.to_string())
+    }
+
+    pub fn storage(e: impl fmt::Display) -> Self {
+        ApiError::Storage(e.to_string())
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::Validation(_) => Status::BadRequest,
+            ApiError::Unauthorized(_) => Status::Unauthorized,
+            ApiError::Forbidden(_) => Status::Forbidden,
+            ApiError::NotFound(_) => Status::NotFound,
+            ApiError::Conflict(_) => Status::Conflict,
+            ApiError::InvalidReference(_) | ApiError::InvalidFields(_) => {
+                Status::UnprocessableEntity
+            }
+            ApiError::TooManyRequests { .. } => Status::TooManyRequests,
+            ApiError::Internal(_) => Status::InternalServerError,
+            ApiError::NotImplemented(_) => Status::NotImplemented,
+            ApiError::Storage(_) => Status::BadGateway,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::InvalidReference(_) => "invalid_reference",
+            ApiError::InvalidFields(_) => "invalid_fields",
+            ApiError::TooManyRequests { .. } => "too_many_requests",
+            ApiError::Internal(_) => "internal_error",
+            ApiError::NotImplemented(_) => "not_implemented",
+            ApiError::Storage(_) => "storage_error",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::Validation(message)
+            | ApiError::Unauthorized(message)
+            | ApiError::Forbidden(message)
+            | ApiError::NotFound(message)
+            | ApiError::Conflict(message)
+            | ApiError::InvalidReference(message)
+            | ApiError::NotImplemented(message) => message.clone(),
+            ApiError::InvalidFields(_) => "Some fields are invalid".to_string(),
+            ApiError::TooManyRequests { .. } => "Too many attempts, try again later".to_string(),
+            ApiError::Internal(_) => "Internal server error".to_string(),
+            ApiError::Storage(_) => "Image storage is unavailable".to_string(),
+        }
+    }
+
+    pub fn details(&self) -> Value {
+        match self {
+            ApiError::InvalidFields(errors) => json!(errors),
+            _ => Value::Null,
+        }
+    }
+
+    fn body(&self) -> Value {
+        json!({
+            "code": self.code(),
+            "message": self.message(),
+            "details": self.details(),
+        })
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Internal(e) | ApiError::Storage(e) => write!(f, "{}: {}", self.code(), e),
+            _ => write!(f, "{}: {}", self.code(), self.message()),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
 
-pub fn server_error(e: Box<dyn Error>) -> Custom<Value> {
-    rocket::error!("{}", e);
-    Custom(Status::InternalServerError, json!("Error"))
+impl From<DieselError> for ApiError {
+    fn from(e: DieselError) -> Self {
+        match e {
+            DieselError::NotFound => ApiError::NotFound("Not found".to_string()),
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                ApiError::Conflict(match info.constraint_name() {
+                    Some(constraint) => format!("Already exists ({})", constraint),
+                    None => "Already exists".to_string(),
+                })
+            }
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) => {
+                ApiError::InvalidReference(match info.constraint_name() {
+                    Some(constraint) => {
+                        format!("Referenced record does not exist ({})", constraint)
+                    }
+                    None => "Referenced record does not exist".to_string(),
+                })
+            }
+            e => ApiError::internal(e),
+        }
+    }
 }
 
-pub fn not_found_error(e: Box<dyn Error>) -> Custom<Value> {
-    rocket::error!("{}", e);
-    Custom(Status::NotFound, json!("Not found"))
+impl From<ImageError> for ApiError {
+    fn from(e: ImageError) -> Self {
+        match e {
+            ImageError::Decode(e) => ApiError::Validation(format!("Cannot decode image: {}", e)),
+            ImageError::TooLarge => ApiError::Validation(ImageError::TooLarge.to_string()),
+            ImageError::Storage(e) => ApiError::Storage(e),
+            ImageError::Database(e) => e.into(),
+        }
+    }
 }
 
-pub fn bad_request_error(e: Box<dyn Error>) -> Custom<Value> {
-    rocket::error!("{}", e);
-    Custom(Status::BadRequest, json!(format!("Bad request: {}", e)))
+/// Undecodable files are reported per file as `image[<index>]`; if the storage failed
+/// for any of them the whole batch is a storage error instead.
+impl From<BatchUploadError> for ApiError {
+    fn from(e: BatchUploadError) -> Self {
+        match e {
+            BatchUploadError::Database(e) => e.into(),
+            BatchUploadError::Files(failed) => {
+                if let Some(file) = failed
+                    .iter()
+                    .find(|file| !matches!(file.error, ImageError::Decode(_)))
+                {
+                    return match &file.error {
+                        ImageError::Storage(e) => ApiError::Storage(e.clone()),
+                        e => ApiError::internal(e),
+                    };
+                }
+
+                ApiError::InvalidFields(
+                    failed
+                        .into_iter()
+                        .map(|file| FieldError {
+                            field: format!("image[{}]", file.index),
+                            message: match file.file_name {
+                                Some(name) => format!("{}: {}", name, file.error),
+                                None => file.error.to_string(),
+                            },
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        if let ApiError::Internal(_) | ApiError::Storage(_) = self {
+            rocket::error!("{}", self);
+        }
+
+        let mut response = Custom(self.status(), self.body()).respond_to(req)?;
+        if let ApiError::TooManyRequests { retry_after } = self {
+            response = Response::build_from(response)
+                .raw_header("Retry-After", retry_after.to_string())
+                .finalize();
+        }
+        Ok(response)
+    }
+}
+
+/// Gives errors raised outside the handlers (failed guards, unknown routes, malformed
+/// bodies) the same JSON shape as `ApiError`.
+#[rocket::catch(default)]
+pub fn default_catcher(status: Status, _req: &Request) -> Custom<Value> {
+    let reason = status.reason().unwrap_or("Error");
+    Custom(
+        status,
+        json!({
+            "code": reason.to_lowercase().replace(' ', "_"),
+            "message": reason,
+            "details": null,
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repositories::image::FailedFile;
+
+    #[test]
+    fn test_diesel_errors_map_to_statuses() {
+        assert_eq!(
+            ApiError::from(DieselError::NotFound).status(),
+            Status::NotFound
+        );
+        assert_eq!(
+            ApiError::from(DieselError::RollbackTransaction).status(),
+            Status::InternalServerError
+        );
+    }
+
+    #[test]
+    fn test_image_errors_map_to_statuses() {
+        assert_eq!(
+            ApiError::from(ImageError::Decode("bad header".to_string())).status(),
+            Status::BadRequest
+        );
+        assert_eq!(
+            ApiError::from(ImageError::Storage("timeout".to_string())).status(),
+            Status::BadGateway
+        );
+    }
+
+    #[test]
+    fn test_batch_upload_errors() {
+        let decode = |index| FailedFile {
+            index,
+            file_name: Some(format!("{}.jpg", index)),
+            error: ImageError::Decode("bad header".to_string()),
+        };
+
+        match ApiError::from(BatchUploadError::Files(vec![decode(0), decode(2)])) {
+            ApiError::InvalidFields(errors) => {
+                let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+                assert_eq!(fields, vec!["image[0]", "image[2]"]);
+                assert!(errors[1].message.starts_with("2.jpg: "));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let storage = FailedFile {
+            index: 1,
+            file_name: None,
+            error: ImageError::Storage("timeout".to_string()),
+        };
+        assert_eq!(
+            ApiError::from(BatchUploadError::Files(vec![decode(0), storage])).status(),
+            Status::BadGateway
+        );
+    }
+
+    #[test]
+    fn test_internal_details_are_hidden() {
+        let error = ApiError::internal("connection refused");
+        assert_eq!(error.body()["message"], "Internal server error");
+        assert_eq!(error.body()["code"], "internal_error");
+    }
 }
@@ -0,0 +1,300 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use diesel_async::AsyncPgConnection;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Orbit, Rocket};
+use rocket_db_pools::Database;
+
+use crate::models::image::Image;
+use crate::repositories::image::ImageRepository;
+use crate::rocket_routes::DbConn;
+use crate::utils::storage::{delete_objects, StorageBackend, StoredObject};
+
+/// Prefix of the keys the images are uploaded under, see `new_upload_key`.
+const UPLOADS_PREFIX: &str = "uploads/";
+
+const DEFAULT_GRACE_HOURS: i64 = 24;
+
+#[derive(Debug, Clone)]
+pub struct GcOptions {
+    /// Rows and objects younger than this are left alone, so an image that is being
+    /// uploaded or saved together with its news, program or gallery is not collected.
+    pub grace_period: Duration,
+    /// Only report what would be deleted.
+    pub dry_run: bool,
+}
+
+impl Default for GcOptions {
+    fn default() -> Self {
+        Self {
+            grace_period: Duration::hours(DEFAULT_GRACE_HOURS),
+            dry_run: false,
+        }
+    }
+}
+
+impl GcOptions {
+    /// Reads `IMAGE_GC_GRACE_HOURS` and `IMAGE_GC_DRY_RUN`.
+    pub fn from_env() -> Result<Self, String> {
+        let grace_hours = match std::env::var("IMAGE_GC_GRACE_HOURS") {
+            Ok(hours) => parse_hours(&hours)?,
+            Err(_) => DEFAULT_GRACE_HOURS,
+        };
+
+        Ok(Self {
+            grace_period: Duration::hours(grace_hours),
+            dry_run: matches!(
+                std::env::var("IMAGE_GC_DRY_RUN").as_deref(),
+                Ok("true") | Ok("1")
+            ),
+        })
+    }
+}
+
+pub fn parse_hours(hours: &str) -> Result<i64, String> {
+    hours
+        .parse::<i64>()
+        .ok()
+        .filter(|hours| *hours >= 0)
+        .ok_or_else(|| format!("Invalid number of hours: {}", hours))
+}
+
+/// What a run deleted, or would have deleted in a dry run.
+#[derive(Debug, Default)]
+pub struct GcReport {
+    pub dry_run: bool,
+    /// Rows nothing points at, deleted together with their variants and objects.
+    pub images: Vec<Image>,
+    /// Objects under `uploads/` without a row.
+    pub objects: Vec<String>,
+    /// Failures that did not stop the run.
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for GcReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.dry_run {
+            "Would delete"
+        } else {
+            "Deleted"
+        };
+
+        writeln!(f, "{} {} unreferenced image(s)", verb, self.images.len())?;
+        for image in &self.images {
+            writeln!(f, "  #{} {}", image.id, image.image_key)?;
+        }
+        writeln!(
+            f,
+            "{} {} object(s) without an image",
+            verb,
+            self.objects.len()
+        )?;
+        for key in &self.objects {
+            writeln!(f, "  {}", key)?;
+        }
+        for error in &self.errors {
+            writeln!(f, "Error: {}", error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Deletes the images nothing points at, then the uploaded objects that have no row.
+/// Each image is deleted in its own transaction, and its objects only once that has
+/// committed; if deleting them fails they are picked up as objects without a row by
+/// the next run.
+pub async fn collect_garbage(
+    c: &mut AsyncPgConnection,
+    storage: &Arc<dyn StorageBackend>,
+    options: &GcOptions,
+) -> Result<GcReport, String> {
+    let cutoff = Utc::now() - options.grace_period;
+    let mut report = GcReport {
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+
+    let images = ImageRepository::find_unreferenced(c, cutoff.naive_utc())
+        .await
+        .map_err(|e| format!("Cannot load unreferenced images: {}", e))?;
+    for image in images {
+        if options.dry_run {
+            report.images.push(image);
+            continue;
+        }
+
+        match ImageRepository::delete_unreferenced_row(c, image.id).await {
+            Ok(Some(keys)) => {
+                if let Err(e) = delete_objects(storage, keys).await {
+                    report.errors.push(format!(
+                        "Cannot delete objects of image #{}: {}",
+                        image.id, e
+                    ));
+                }
+                report.images.push(image);
+            }
+            Ok(None) => {}
+            Err(e) => report
+                .errors
+                .push(format!("Cannot delete image #{}: {}", image.id, e)),
+        }
+    }
+
+    // Load the keys before listing, so an object uploaded in between is too young
+    // rather than unknown
+    let known_keys = ImageRepository::find_all_keys(c)
+        .await
+        .map_err(|e| format!("Cannot load image keys: {}", e))?;
+    let objects = storage
+        .list(UPLOADS_PREFIX)
+        .await
+        .map_err(|e| format!("Cannot list uploads: {}", e))?;
+
+    report.objects = orphaned_objects(objects, &known_keys, cutoff);
+    report.objects.sort();
+    if !options.dry_run {
+        for key in &report.objects {
+            if let Err(e) = storage.delete(key).await {
+                report
+                    .errors
+                    .push(format!("Cannot delete object {}: {}", key, e));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Keys without a row last modified before `cutoff`. Objects whose age is unknown are
+/// kept.
+fn orphaned_objects(
+    objects: Vec<StoredObject>,
+    known_keys: &HashSet<String>,
+    cutoff: DateTime<Utc>,
+) -> Vec<String> {
+    objects
+        .into_iter()
+        .filter(|object| !known_keys.contains(&object.key))
+        .filter(|object| object.last_modified.is_some_and(|time| time < cutoff))
+        .map(|object| object.key)
+        .collect()
+}
+
+/// Runs `collect_garbage` every `interval` once the server is up. Attached only when
+/// `IMAGE_GC_INTERVAL_HOURS` is set.
+pub struct ImageGc {
+    interval: std::time::Duration,
+    options: GcOptions,
+}
+
+impl ImageGc {
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let interval_hours = match std::env::var("IMAGE_GC_INTERVAL_HOURS") {
+            Ok(hours) if !hours.is_empty() => parse_hours(&hours)?,
+            _ => return Ok(None),
+        };
+        if interval_hours == 0 {
+            return Err("IMAGE_GC_INTERVAL_HOURS must be at least 1".to_string());
+        }
+
+        Ok(Some(Self {
+            interval: std::time::Duration::from_secs(interval_hours as u64 * 3600),
+            options: GcOptions::from_env()?,
+        }))
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for ImageGc {
+    fn info(&self) -> Info {
+        Info {
+            name: "Delete orphaned images periodically",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let (Some(db), Some(storage)) = (
+            DbConn::fetch(rocket),
+            rocket.state::<Arc<dyn StorageBackend>>(),
+        ) else {
+            rocket::error!("Image GC needs the database and the storage, not starting it");
+            return;
+        };
+        let pool = (**db).clone();
+        let storage = storage.clone();
+        let interval = self.interval;
+        let options = self.options.clone();
+
+        rocket::tokio::spawn(async move {
+            let mut timer = rocket::tokio::time::interval(interval);
+            loop {
+                timer.tick().await;
+
+                let result = match pool.get().await {
+                    Ok(mut c) => collect_garbage(&mut c, &storage, &options).await,
+                    Err(e) => Err(format!("Cannot connect to the database: {}", e)),
+                };
+                match result {
+                    Ok(report) if report.errors.is_empty() => rocket::info!("{}", report),
+                    Ok(report) => rocket::warn!("{}", report),
+                    Err(e) => rocket::error!("Image GC failed: {}", e),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(key: &str, hours_ago: Option<i64>) -> StoredObject {
+        StoredObject {
+            key: key.to_string(),
+            last_modified: hours_ago.map(|hours| Utc::now() - Duration::hours(hours)),
+        }
+    }
+
+    #[test]
+    fn test_only_old_unknown_objects_are_orphaned() {
+        let known_keys = HashSet::from(["uploads/known.jpg".to_string()]);
+        let objects = vec![
+            object("uploads/known.jpg", Some(48)),
+            object("uploads/old.jpg", Some(48)),
+            object("uploads/new.jpg", Some(1)),
+            object("uploads/undated.jpg", None),
+        ];
+
+        let orphaned = orphaned_objects(objects, &known_keys, Utc::now() - Duration::hours(24));
+
+        assert_eq!(orphaned, vec!["uploads/old.jpg"]);
+    }
+
+    #[test]
+    fn test_parse_hours() {
+        assert_eq!(parse_hours("0"), Ok(0));
+        assert_eq!(parse_hours("12"), Ok(12));
+        assert!(parse_hours("-1").is_err());
+        assert!(parse_hours("soon").is_err());
+    }
+
+    #[test]
+    fn test_dry_run_report() {
+        let report = GcReport {
+            dry_run: true,
+            objects: vec!["uploads/a.jpg".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            report.to_string(),
+            "Would delete 0 unreferenced image(s)\n\
+             Would delete 1 object(s) without an image\n  uploads/a.jpg\n"
+        );
+    }
+}
@@ -1,6 +1,9 @@
+use rocket::fs::{FileServer, Options};
 use rocket_db_pools::Database;
 
+mod commands;
 pub mod errors;
+mod image_gc;
 pub mod models;
 pub mod repositories;
 pub mod rocket_routes;
@@ -9,7 +12,31 @@ pub mod utils;
 
 #[rocket::main]
 async fn main() {
-    let _ = rocket::build()
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = commands::run(&args).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let storage = utils::storage::storage_from_env()
+        .await
+        .expect("Invalid storage config");
+
+    let mut rocket = rocket::build();
+    if let Some(dir) = utils::storage::local_storage_dir() {
+        rocket = rocket.mount(
+            utils::storage::LOCAL_FILES_PATH,
+            FileServer::new(dir, Options::Missing),
+        );
+    }
+    if let Some(gc) = image_gc::ImageGc::from_env().expect("Invalid image GC config") {
+        rocket = rocket.attach(gc);
+    }
+
+    let _ = rocket
         .mount(
             "/",
             rocket::routes![
@@ -18,22 +45,68 @@ async fn main() {
                 rocket_routes::events::delete_event,
                 rocket_routes::events::update_event,
                 rocket_routes::events::get_event_with_program,
+                rocket_routes::events::get_event_participants,
                 rocket_routes::programs::get_programs_for_event,
                 rocket_routes::programs::create_program,
+                rocket_routes::programs::create_program_json,
                 rocket_routes::programs::delete_program,
                 rocket_routes::programs::update_program,
+                rocket_routes::programs::update_program_json,
                 rocket_routes::news::get_news,
                 rocket_routes::news::get_all_news,
                 rocket_routes::news::create_news,
+                rocket_routes::news::create_news_json,
                 rocket_routes::news::update_news,
+                rocket_routes::news::update_news_json,
                 rocket_routes::news::delete_news,
                 rocket_routes::gallery::get_gallery,
                 rocket_routes::gallery::get_all_galleries,
                 rocket_routes::gallery::create_gallery,
+                rocket_routes::gallery::create_gallery_json,
                 rocket_routes::gallery::update_gallery,
+                rocket_routes::gallery::update_gallery_json,
                 rocket_routes::gallery::delete_gallery,
+                rocket_routes::gallery::add_gallery_images,
+                rocket_routes::gallery::remove_gallery_images,
+                rocket_routes::images::get_images,
+                rocket_routes::images::get_image,
+                rocket_routes::images::upload_image,
+                rocket_routes::images::presign_upload,
+                rocket_routes::images::complete_upload,
+                rocket_routes::images::delete_image,
+                rocket_routes::auth::register,
+                rocket_routes::auth::login,
+                rocket_routes::auth::refresh,
+                rocket_routes::auth::logout,
+                rocket_routes::auth::verify_email,
+                rocket_routes::auth::resend_verification_email,
+                rocket_routes::auth::request_password_reset,
+                rocket_routes::auth::confirm_password_reset,
+                rocket_routes::users::get_me,
+                rocket_routes::users::get_my_sessions,
+                rocket_routes::users::revoke_my_session,
+                rocket_routes::users::update_me,
+                rocket_routes::users::update_user,
+                rocket_routes::admin::get_users,
+                rocket_routes::admin::update_user_role,
+                rocket_routes::admin::disable_user,
+                rocket_routes::admin::unlock_user,
+                rocket_routes::admin::enable_user,
+                rocket_routes::admin::get_user_sessions,
+                rocket_routes::admin::revoke_user_sessions,
+                rocket_routes::admin::revoke_user_session,
+                rocket_routes::admin::delete_user,
+                rocket_routes::applications::submit_application,
+                rocket_routes::applications::get_pending_applications,
+                rocket_routes::applications::confirm_application,
+                rocket_routes::applications::reject_application,
             ],
         )
+        .register("/", rocket::catchers![errors::default_catcher])
+        .manage(utils::auth_token::TokenSigner::from_env().expect("Invalid auth token config"))
+        .manage(utils::mailer::mailer_from_env().expect("Invalid mailer config"))
+        .manage(storage)
+        .manage(utils::rate_limiter::LoginRateLimiter::default())
         .attach(rocket_routes::DbConn::init())
         .attach(rocket_routes::Cors)
         .launch()
@@ -3,7 +3,19 @@ use diesel::prelude::*;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::errors::ApiError;
 use crate::schema::events;
+use crate::utils::validation::{Rule, Validate, Validator};
+
+const TITLE_RULES: &[Rule] = &[Rule::NotEmpty, Rule::MaxLength(128)];
+const YEAR_RULES: &[Rule] = &[Rule::Range(2000, 2100)];
+
+fn validate_event(title: &str, year: i32) -> Result<(), ApiError> {
+    let mut validator = Validator::new();
+    validator.text("title", title, TITLE_RULES);
+    validator.number("year", year, YEAR_RULES);
+    validator.finish()
+}
 
 #[derive(AsChangeset, Queryable, Serialize, Deserialize, Debug)]
 pub struct Event {
@@ -36,3 +48,15 @@ pub struct NewEvent {
     pub year: i32,
     pub is_current: bool,
 }
+
+impl Validate for NewEvent {
+    fn validate(&self) -> Result<(), ApiError> {
+        validate_event(&self.title, self.year)
+    }
+}
+
+impl Validate for Event {
+    fn validate(&self) -> Result<(), ApiError> {
+        validate_event(&self.title, self.year)
+    }
+}
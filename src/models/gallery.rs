@@ -1,14 +1,16 @@
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use rocket::response::status::Custom;
-use rocket::serde::json::Value;
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::models::image::Image;
+use crate::errors::ApiError;
+use crate::models::image::{Image, ImageWithVariants};
 use crate::schema::{galleries, gallery_images};
 use crate::utils::form_data::{FormData, FromFormData};
 use crate::utils::form_fields::FormFields;
+use crate::utils::validation::Rule;
+
+const NAME_RULES: &[Rule] = &[Rule::NotEmpty, Rule::MaxLength(256)];
 
 #[derive(Queryable, Serialize, Deserialize, Debug)]
 #[diesel(table_name = galleries)]
@@ -20,6 +22,14 @@ pub struct Gallery {
     pub updated_at: NaiveDateTime,
 }
 
+/// A gallery together with the images added to it, in the order they were uploaded.
+#[derive(Serialize, Debug)]
+pub struct GalleryWithImages {
+    #[serde(flatten)]
+    pub gallery: Gallery,
+    pub images: Vec<ImageWithVariants>,
+}
+
 #[derive(Insertable, Deserialize)]
 #[diesel(table_name = galleries)]
 pub struct NewGallery {
@@ -34,13 +44,19 @@ impl FormFields for NewGallery {
     fn has_image() -> bool {
         true
     }
+    fn image_id_field() -> &'static str {
+        "featured_image_id"
+    }
+    fn get_field_rules() -> Vec<(&'static str, &'static [Rule])> {
+        vec![("name", NAME_RULES)]
+    }
 }
 
 impl FromFormData for NewGallery {
-    fn from_form_data(form_data: FormData) -> Result<Self, Custom<Value>> {
+    fn from_form_data(form_data: FormData) -> Result<Self, ApiError> {
         Ok(Self {
             name: form_data.required_text_values["name"].clone(),
-            featured_image_id: 0,
+            featured_image_id: form_data.image_id.unwrap_or_default(),
         })
     }
 }
@@ -52,6 +68,13 @@ pub struct UpdateGallery {
     pub featured_image_id: Option<i32>,
 }
 
+impl UpdateGallery {
+    /// Whether the update sets no column, which diesel refuses to run.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.featured_image_id.is_none()
+    }
+}
+
 impl FormFields for UpdateGallery {
     fn get_optional_text_fields() -> Vec<&'static str> {
         vec!["name"]
@@ -62,17 +85,23 @@ impl FormFields for UpdateGallery {
     fn is_image_required() -> bool {
         false
     }
+    fn image_id_field() -> &'static str {
+        "featured_image_id"
+    }
+    fn get_field_rules() -> Vec<(&'static str, &'static [Rule])> {
+        vec![("name", NAME_RULES)]
+    }
 }
 
 impl FromFormData for UpdateGallery {
-    fn from_form_data(form_data: FormData) -> Result<Self, Custom<Value>> {
+    fn from_form_data(form_data: FormData) -> Result<Self, ApiError> {
         Ok(Self {
             name: form_data
                 .optional_text_values
                 .get("name")
                 .cloned()
                 .flatten(),
-            featured_image_id: None,
+            featured_image_id: form_data.image_id,
         })
     }
 }
@@ -93,3 +122,17 @@ pub struct NewGalleryImage {
     pub gallery_id: i32,
     pub image_id: i32,
 }
+
+/// A multipart upload of several `image` parts to add to a gallery.
+pub struct GalleryImagesUpload;
+
+impl FormFields for GalleryImagesUpload {
+    fn max_images() -> u32 {
+        20
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GalleryImageIds {
+    pub image_ids: Vec<i32>,
+}
@@ -3,9 +3,13 @@ use diesel::prelude::*;
 use serde::Deserialize;
 use serde::Serialize;
 
-use crate::schema::images;
+use crate::errors::ApiError;
+use crate::schema::{image_variants, images};
+use crate::utils::form_fields::FormFields;
+use crate::utils::validation::{Rule, Validate, Validator};
 
-#[derive(Queryable, Serialize, Deserialize, Debug)]
+#[derive(Queryable, Identifiable, Serialize, Deserialize, Debug)]
+#[diesel(table_name = images)]
 pub struct Image {
     pub id: i32,
     pub image_url: String,
@@ -14,6 +18,9 @@ pub struct Image {
     pub height: i32,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    /// Uploaded to the image library rather than with a news, program, gallery or user.
+    /// Such images stay until deleted explicitly, even when nothing uses them.
+    pub in_library: bool,
 }
 
 #[derive(Insertable, Deserialize)]
@@ -23,4 +30,82 @@ pub struct NewImage {
     pub image_key: String,
     pub width: i32,
     pub height: i32,
+    pub in_library: bool,
+}
+
+/// A resized copy of an image, see `utils::image_variants`.
+#[derive(Queryable, Identifiable, Associations, Serialize, Debug)]
+#[diesel(belongs_to(Image))]
+#[diesel(table_name = image_variants)]
+pub struct ImageVariant {
+    pub id: i32,
+    #[serde(skip)]
+    pub image_id: i32,
+    pub name: String,
+    pub format: String,
+    pub image_url: String,
+    pub image_key: String,
+    pub width: i32,
+    pub height: i32,
+    #[serde(skip)]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = image_variants)]
+pub struct NewImageVariant {
+    pub image_id: i32,
+    pub name: String,
+    pub format: String,
+    pub image_url: String,
+    pub image_key: String,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// An image as the API returns it. `variants` is ordered by width, so each format's
+/// entries can be joined into a `srcset` as `"<image_url> <width>w"`.
+#[derive(Serialize, Debug)]
+pub struct ImageWithVariants {
+    #[serde(flatten)]
+    pub image: Image,
+    pub variants: Vec<ImageVariant>,
+}
+
+/// A multipart upload to the image library: just the `image` file.
+pub struct ImageUpload;
+
+impl FormFields for ImageUpload {}
+
+/// Query parameters of the image library listing.
+#[derive(rocket::FromForm, Debug)]
+pub struct ImageFilters {
+    #[field(default = 1, validate = range(1..))]
+    pub page: i64,
+    #[field(default = 20, validate = range(1..=100))]
+    pub per_page: i64,
+}
+
+/// Asks for a URL to upload one file straight to the storage.
+#[derive(Deserialize, Debug)]
+pub struct PresignUpload {
+    pub file_name: String,
+    pub content_type: String,
+}
+
+impl Validate for PresignUpload {
+    fn validate(&self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.text("file_name", &self.file_name, &[Rule::NotEmpty]);
+        if !self.content_type.starts_with("image/") {
+            validator.error("content_type", "must be an image type");
+        }
+        validator.finish()
+    }
+}
+
+/// Registers a file uploaded with a presigned URL.
+#[derive(Deserialize, Debug)]
+pub struct CompleteUpload {
+    pub key: String,
 }
@@ -1,6 +1,10 @@
+pub mod email_verification_token;
 pub mod event;
 pub mod gallery;
 pub mod image;
 pub mod news;
+pub mod one_time_token;
+pub mod password_reset_token;
 pub mod program;
+pub mod session;
 pub mod user;
@@ -1,13 +1,16 @@
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use rocket::response::status::Custom;
-use rocket::serde::json::Value;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::errors::ApiError;
 use crate::schema::news;
 use crate::utils::form_data::{FormData, FromFormData};
 use crate::utils::form_fields::FormFields;
+use crate::utils::validation::Rule;
+
+const TITLE_RULES: &[Rule] = &[Rule::NotEmpty, Rule::MaxLength(256)];
+const MESSAGE_RULES: &[Rule] = &[Rule::NotEmpty];
 
 #[derive(AsChangeset, Queryable, Serialize, Deserialize, Debug)]
 #[diesel(table_name = news)]
@@ -35,14 +38,17 @@ impl FormFields for NewNews {
     fn has_image() -> bool {
         true
     }
+    fn get_field_rules() -> Vec<(&'static str, &'static [Rule])> {
+        vec![("title", TITLE_RULES), ("message", MESSAGE_RULES)]
+    }
 }
 
 impl FromFormData for NewNews {
-    fn from_form_data(form_data: FormData) -> Result<Self, Custom<Value>> {
+    fn from_form_data(form_data: FormData) -> Result<Self, ApiError> {
         Ok(Self {
             title: form_data.required_text_values["title"].clone(),
             message: form_data.required_text_values["message"].clone(),
-            image_id: 0, // Will be set after image upload
+            image_id: form_data.image_id.unwrap_or_default(), // Set after upload for multipart forms
         })
     }
 }
@@ -55,6 +61,13 @@ pub struct UpdateNews {
     pub image_id: Option<i32>,
 }
 
+impl UpdateNews {
+    /// Whether the update sets no column, which diesel refuses to run.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.message.is_none() && self.image_id.is_none()
+    }
+}
+
 impl FormFields for UpdateNews {
     fn get_optional_text_fields() -> Vec<&'static str> {
         vec!["title", "message"]
@@ -65,10 +78,13 @@ impl FormFields for UpdateNews {
     fn is_image_required() -> bool {
         false
     }
+    fn get_field_rules() -> Vec<(&'static str, &'static [Rule])> {
+        vec![("title", TITLE_RULES), ("message", MESSAGE_RULES)]
+    }
 }
 
 impl FromFormData for UpdateNews {
-    fn from_form_data(form_data: FormData) -> Result<Self, Custom<Value>> {
+    fn from_form_data(form_data: FormData) -> Result<Self, ApiError> {
         Ok(Self {
             title: form_data
                 .optional_text_values
@@ -80,7 +96,7 @@ impl FromFormData for UpdateNews {
                 .get("message")
                 .cloned()
                 .flatten(),
-            image_id: None,
+            image_id: form_data.image_id,
         })
     }
 }
@@ -0,0 +1,14 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// A row of `email_verification_tokens` or `password_reset_tokens`, which share
+/// their columns.
+#[derive(Queryable, Debug)]
+pub struct OneTimeToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+    pub used_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
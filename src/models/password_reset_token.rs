@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+use crate::errors::ApiError;
+use crate::utils::validation::{Rule, Validate, Validator};
+
+#[derive(Deserialize)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct PasswordResetConfirmation {
+    pub token: String,
+    pub password: String,
+}
+
+impl Validate for PasswordResetConfirmation {
+    fn validate(&self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.text("password", &self.password, &[Rule::NotEmpty]);
+        validator.finish()
+    }
+}
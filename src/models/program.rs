@@ -1,14 +1,17 @@
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use rocket::response::status::Custom;
-use rocket::serde::json::Value;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::errors::ApiError;
 use crate::schema::programs;
 use crate::utils::form_data::FormData;
 use crate::utils::form_data::FromFormData;
 use crate::utils::form_fields::FormFields;
+use crate::utils::validation::Rule;
+
+const TITLE_RULES: &[Rule] = &[Rule::NotEmpty, Rule::MaxLength(128)];
+const TEXT_RULES: &[Rule] = &[Rule::NotEmpty];
 
 #[derive(AsChangeset, Queryable, Serialize, Deserialize, Debug)]
 pub struct Program {
@@ -43,15 +46,18 @@ impl FormFields for NewProgram {
     fn has_image() -> bool {
         true
     }
+    fn get_field_rules() -> Vec<(&'static str, &'static [Rule])> {
+        vec![("title", TITLE_RULES), ("text", TEXT_RULES)]
+    }
 }
 
 impl FromFormData for NewProgram {
-    fn from_form_data(form_data: FormData) -> Result<Self, Custom<Value>> {
+    fn from_form_data(form_data: FormData) -> Result<Self, ApiError> {
         Ok(Self {
             title: form_data.required_text_values["title"].clone(),
             text: form_data.required_text_values["text"].clone(),
             event_id: form_data.required_number_values["event_id"],
-            image_id: 0, // Will be set after image upload
+            image_id: form_data.image_id.unwrap_or_default(), // Set after upload for multipart forms
         })
     }
 }
@@ -64,6 +70,13 @@ pub struct UpdateProgram {
     pub image_id: Option<i32>,
 }
 
+impl UpdateProgram {
+    /// Whether the update sets no column, which diesel refuses to run.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.text.is_none() && self.image_id.is_none()
+    }
+}
+
 impl FormFields for UpdateProgram {
     fn get_optional_text_fields() -> Vec<&'static str> {
         vec!["title", "text"]
@@ -74,10 +87,13 @@ impl FormFields for UpdateProgram {
     fn is_image_required() -> bool {
         false
     }
+    fn get_field_rules() -> Vec<(&'static str, &'static [Rule])> {
+        vec![("title", TITLE_RULES), ("text", TEXT_RULES)]
+    }
 }
 
 impl FromFormData for UpdateProgram {
-    fn from_form_data(form_data: FormData) -> Result<Self, Custom<Value>> {
+    fn from_form_data(form_data: FormData) -> Result<Self, ApiError> {
         Ok(Self {
             title: form_data
                 .optional_text_values
@@ -89,7 +105,7 @@ impl FromFormData for UpdateProgram {
                 .get("text")
                 .cloned()
                 .flatten(),
-            image_id: None, // Will be set after image upload if present
+            image_id: form_data.image_id,
         })
     }
 }
@@ -1,3 +1,4 @@
+use std::fmt;
 use std::io::Write;
 use std::str::FromStr;
 
@@ -10,53 +11,203 @@ use diesel::{deserialize::FromSqlRow, expression::AsExpression, prelude::*, sql_
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::errors::ApiError;
 use crate::schema::users;
+use crate::utils::form_data::{FormData, FromFormData};
+use crate::utils::form_fields::FormFields;
+use crate::utils::validation::{Rule, Validate, Validator};
+
+const USERNAME_RULES: &[Rule] = &[Rule::NotEmpty, Rule::MaxLength(64)];
+const EMAIL_RULES: &[Rule] = &[Rule::NotEmpty, Rule::MaxLength(64), Rule::Email];
+const PHONE_NUMBER_RULES: &[Rule] = &[Rule::MaxLength(64)];
 
 #[derive(Queryable, Serialize, Deserialize, Debug, AsChangeset)]
 pub struct User {
     pub id: i32,
     pub username: String,
     pub email: String,
+    #[serde(skip_serializing)]
     pub password: String,
     pub phone_number: String,
-    pub description: String,
-    pub role: UserRole,
+    pub description: Option<String>,
     pub user_type: UserType,
-    pub request: RequestStatus,
+    pub role: UserRole,
     pub event_id: Option<i32>,
-    pub image_id: i32,
+    pub request: RequestStatus,
+    pub image_id: Option<i32>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub disabled_at: Option<NaiveDateTime>,
+    pub email_verified_at: Option<NaiveDateTime>,
+    pub locked_until: Option<NaiveDateTime>,
 }
 
 #[derive(Insertable)]
-#[table_name = "users"]
+#[diesel(table_name = users)]
 pub struct NewUser {
     pub username: String,
     pub email: String,
     pub password: String,
     pub phone_number: String,
-    pub description: String,
-    pub role: UserRole,
+    pub description: Option<String>,
     pub user_type: UserType,
+    pub role: UserRole,
+    pub event_id: Option<i32>,
     pub request: RequestStatus,
+    pub image_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct RegisterUser {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+    pub phone_number: String,
+    pub description: Option<String>,
+    pub user_type: UserType,
+}
+
+impl Validate for RegisterUser {
+    fn validate(&self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.text("username", &self.username, USERNAME_RULES);
+        validator.text("email", &self.email, EMAIL_RULES);
+        validator.text("password", &self.password, &[Rule::NotEmpty]);
+        validator.text("phone_number", &self.phone_number, PHONE_NUMBER_RULES);
+        validator.finish()
+    }
+}
+
+/// The public view of a confirmed festival participant.
+#[derive(Queryable, Serialize, Debug)]
+pub struct Participant {
+    pub username: String,
+    pub description: Option<String>,
+    pub user_type: UserType,
+    pub image_url: Option<String>,
+}
+
+#[derive(AsChangeset, Deserialize)]
+#[diesel(table_name = users)]
+pub struct UpdateProfile {
+    pub username: Option<String>,
+    pub phone_number: Option<String>,
+    pub description: Option<String>,
+    #[serde(skip_deserializing)]
+    pub image_id: Option<i32>,
+}
+
+impl UpdateProfile {
+    /// Whether the update sets no column, which diesel refuses to run.
+    pub fn is_empty(&self) -> bool {
+        self.username.is_none()
+            && self.phone_number.is_none()
+            && self.description.is_none()
+            && self.image_id.is_none()
+    }
+}
+
+impl FormFields for UpdateProfile {
+    fn get_optional_text_fields() -> Vec<&'static str> {
+        vec!["username", "phone_number", "description"]
+    }
+    fn has_image() -> bool {
+        true
+    }
+    fn is_image_required() -> bool {
+        false
+    }
+    fn get_field_rules() -> Vec<(&'static str, &'static [Rule])> {
+        vec![
+            ("username", USERNAME_RULES),
+            ("phone_number", PHONE_NUMBER_RULES),
+        ]
+    }
+}
+
+impl Validate for UpdateProfile {
+    fn validate(&self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        if let Some(username) = &self.username {
+            validator.text("username", username, USERNAME_RULES);
+        }
+        if let Some(phone_number) = &self.phone_number {
+            validator.text("phone_number", phone_number, PHONE_NUMBER_RULES);
+        }
+        validator.finish()
+    }
+}
+
+impl FromFormData for UpdateProfile {
+    fn from_form_data(form_data: FormData) -> Result<Self, ApiError> {
+        Ok(Self {
+            username: form_data
+                .optional_text_values
+                .get("username")
+                .cloned()
+                .flatten(),
+            phone_number: form_data
+                .optional_text_values
+                .get("phone_number")
+                .cloned()
+                .flatten(),
+            description: form_data
+                .optional_text_values
+                .get("description")
+                .cloned()
+                .flatten(),
+            image_id: None, // Will be set after image upload if present
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateRole {
+    pub role: UserRole,
+}
+
+/// Query parameters of the admin user listing.
+#[derive(rocket::FromForm, Debug)]
+pub struct UserFilters {
+    pub role: Option<UserRole>,
+    pub user_type: Option<UserType>,
+    pub request: Option<RequestStatus>,
     pub event_id: Option<i32>,
-    pub image_id: i32,
+    /// Case-insensitive match on username or email.
+    pub search: Option<String>,
+    #[field(default = 1, validate = range(1..))]
+    pub page: i64,
+    #[field(default = 20, validate = range(1..=100))]
+    pub per_page: i64,
+}
+
+#[derive(Deserialize)]
+pub struct Credentials {
+    pub email: String,
+    pub password: String,
 }
 
-#[derive(AsExpression, Debug, FromSqlRow, Serialize, Deserialize)]
+/// The form emails are stored, looked up and rate limited in.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+#[derive(AsExpression, Debug, Clone, Copy, PartialEq, Eq, FromSqlRow, Serialize, Deserialize)]
 #[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+#[derive(rocket::FromFormField)]
 pub enum UserRole {
     Admin,
     User,
 }
 
-impl ToString for UserRole {
-    fn to_string(&self) -> String {
-        match self {
-            UserRole::Admin => String::from("admin"),
-            UserRole::User => String::from("user"),
-        }
+impl fmt::Display for UserRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            UserRole::Admin => "admin",
+            UserRole::User => "user",
+        };
+        write!(f, "{}", value)
     }
 }
 
@@ -95,19 +246,22 @@ impl ToSql<Text, Pg> for UserRole {
     }
 }
 
-#[derive(AsExpression, Debug, FromSqlRow, Serialize, Deserialize)]
+#[derive(AsExpression, Debug, Clone, Copy, PartialEq, Eq, FromSqlRow, Serialize, Deserialize)]
 #[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+#[derive(rocket::FromFormField)]
 pub enum UserType {
     Seller,
     Artist,
 }
 
-impl ToString for UserType {
-    fn to_string(&self) -> String {
-        match self {
-            UserType::Seller => String::from("seller"),
-            UserType::Artist => String::from("artist"),
-        }
+impl fmt::Display for UserType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            UserType::Seller => "seller",
+            UserType::Artist => "artist",
+        };
+        write!(f, "{}", value)
     }
 }
 
@@ -146,8 +300,10 @@ impl ToSql<Text, Pg> for UserType {
     }
 }
 
-#[derive(AsExpression, Debug, FromSqlRow, Serialize, Deserialize)]
+#[derive(AsExpression, Debug, Clone, Copy, PartialEq, Eq, FromSqlRow, Serialize, Deserialize)]
 #[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+#[derive(rocket::FromFormField)]
 pub enum RequestStatus {
     NotSend,
     Pending,
@@ -155,14 +311,27 @@ pub enum RequestStatus {
     Rejected,
 }
 
-impl ToString for RequestStatus {
-    fn to_string(&self) -> String {
-        match self {
-            RequestStatus::NotSend => String::from("notsend"),
-            RequestStatus::Pending => String::from("pending"),
-            RequestStatus::Confirmed => String::from("confirmed"),
-            RequestStatus::Rejected => String::from("rejected"),
-        }
+impl RequestStatus {
+    /// Applications go NotSend -> Pending, then an admin confirms or rejects them.
+    pub fn can_transition_to(&self, next: RequestStatus) -> bool {
+        matches!(
+            (self, next),
+            (RequestStatus::NotSend, RequestStatus::Pending)
+                | (RequestStatus::Pending, RequestStatus::Confirmed)
+                | (RequestStatus::Pending, RequestStatus::Rejected)
+        )
+    }
+}
+
+impl fmt::Display for RequestStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            RequestStatus::NotSend => "notsend",
+            RequestStatus::Pending => "pending",
+            RequestStatus::Confirmed => "confirmed",
+            RequestStatus::Rejected => "rejected",
+        };
+        write!(f, "{}", value)
     }
 }
 
@@ -206,3 +375,20 @@ impl ToSql<Text, Pg> for RequestStatus {
         Ok(diesel::serialize::IsNull::No)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_status_transitions() {
+        assert!(RequestStatus::NotSend.can_transition_to(RequestStatus::Pending));
+        assert!(RequestStatus::Pending.can_transition_to(RequestStatus::Confirmed));
+        assert!(RequestStatus::Pending.can_transition_to(RequestStatus::Rejected));
+
+        assert!(!RequestStatus::NotSend.can_transition_to(RequestStatus::Confirmed));
+        assert!(!RequestStatus::Pending.can_transition_to(RequestStatus::Pending));
+        assert!(!RequestStatus::Confirmed.can_transition_to(RequestStatus::Rejected));
+        assert!(!RequestStatus::Rejected.can_transition_to(RequestStatus::Confirmed));
+    }
+}
@@ -12,6 +12,14 @@ impl EventRepository {
         events::table.find(id).get_result(c).await
     }
 
+    pub async fn find_current(c: &mut AsyncPgConnection) -> QueryResult<Event> {
+        events::table
+            .filter(events::is_current.eq(true))
+            .order((events::year.desc(), events::id.desc()))
+            .first(c)
+            .await
+    }
+
     pub async fn find_event_with_program(
         c: &mut AsyncPgConnection,
         id: i32,
@@ -1,9 +1,14 @@
 use diesel::prelude::*;
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
 
-use crate::models::gallery::{Gallery, NewGallery, NewGalleryImage, UpdateGallery};
+use crate::models::gallery::{
+    Gallery, GalleryWithImages, NewGallery, NewGalleryImage, UpdateGallery,
+};
+use crate::models::image::{Image, ImageWithVariants};
+use crate::repositories::image::ImageRepository;
 use crate::schema::galleries;
 use crate::schema::gallery_images;
+use crate::schema::images;
 
 pub struct GalleryRepository;
 
@@ -12,6 +17,39 @@ impl GalleryRepository {
         galleries::table.find(id).get_result(c).await
     }
 
+    pub async fn find_with_images(
+        c: &mut AsyncPgConnection,
+        id: i32,
+    ) -> QueryResult<GalleryWithImages> {
+        let gallery = Self::find(c, id).await?;
+        let images = Self::find_images(c, id).await?;
+
+        Ok(GalleryWithImages { gallery, images })
+    }
+
+    pub async fn find_images(
+        c: &mut AsyncPgConnection,
+        id: i32,
+    ) -> QueryResult<Vec<ImageWithVariants>> {
+        let images: Vec<Image> = gallery_images::table
+            .inner_join(images::table)
+            .filter(gallery_images::gallery_id.eq(id))
+            .select(images::all_columns)
+            .order(images::id.asc())
+            .load(c)
+            .await?;
+
+        ImageRepository::with_variants(c, images).await
+    }
+
+    pub async fn find_image_ids(c: &mut AsyncPgConnection, id: i32) -> QueryResult<Vec<i32>> {
+        gallery_images::table
+            .filter(gallery_images::gallery_id.eq(id))
+            .select(gallery_images::image_id)
+            .load(c)
+            .await
+    }
+
     pub async fn all(c: &mut AsyncPgConnection) -> QueryResult<Vec<Gallery>> {
         galleries::table.load(c).await
     }
@@ -26,18 +64,27 @@ impl GalleryRepository {
             .await
     }
 
+    /// An update without any field returns the gallery unchanged.
     pub async fn update(
         c: &mut AsyncPgConnection,
         id: i32,
         gallery: UpdateGallery,
     ) -> QueryResult<Gallery> {
+        if gallery.is_empty() {
+            return Self::find(c, id).await;
+        }
+
         diesel::update(galleries::table.find(id))
             .set(&gallery)
             .get_result(c)
             .await
     }
 
+    /// Deletes the gallery together with the list of its images, but not the images.
     pub async fn delete(c: &mut AsyncPgConnection, id: i32) -> QueryResult<usize> {
+        diesel::delete(gallery_images::table.filter(gallery_images::gallery_id.eq(id)))
+            .execute(c)
+            .await?;
         diesel::delete(galleries::table.find(id)).execute(c).await
     }
 
@@ -61,11 +108,12 @@ impl GalleryRepository {
             .await
     }
 
+    /// Returns the ids of the images that were in the gallery and are removed now.
     pub async fn remove_images(
         c: &mut AsyncPgConnection,
         gallery_id: i32,
         image_ids: Vec<i32>,
-    ) -> QueryResult<usize> {
+    ) -> QueryResult<Vec<i32>> {
         diesel::delete(
             gallery_images::table.filter(
                 gallery_images::gallery_id
@@ -73,7 +121,8 @@ impl GalleryRepository {
                     .and(gallery_images::image_id.eq_any(image_ids)),
             ),
         )
-        .execute(c)
+        .returning(gallery_images::image_id)
+        .get_results(c)
         .await
     }
 }
@@ -1,118 +1,856 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+
+use chrono::NaiveDateTime;
+use diesel::dsl::{exists, not};
 use diesel::prelude::*;
-use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+use crate::models::image::{
+    Image, ImageFilters, ImageVariant, ImageWithVariants, NewImage, NewImageVariant,
+};
+use crate::schema::{galleries, gallery_images, image_variants, images, news, programs, users};
+use crate::utils::form_fields::{ImageFormData, MAX_IMAGE_SIZE};
+use crate::utils::image_variants::{generate_variants, EncodedVariant};
+use crate::utils::storage::{
+    delete_objects, upload_image, upload_objects, StorageBackend, UploadedImage,
+};
+
+#[derive(Debug)]
+pub enum ImageError {
+    /// The uploaded bytes are not an image we can decode.
+    Decode(String),
+    /// A direct upload is over `MAX_IMAGE_SIZE`.
+    TooLarge,
+    /// The object storage rejected or failed the request.
+    Storage(String),
+    Database(diesel::result::Error),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Decode(e) => write!(f, "Cannot decode image: {}", e),
+            ImageError::TooLarge => write!(
+                f,
+                "Image is larger than {} MB",
+                MAX_IMAGE_SIZE / 1024 / 1024
+            ),
+            ImageError::Storage(e) => write!(f, "Image storage failed: {}", e),
+            ImageError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<diesel::result::Error> for ImageError {
+    fn from(e: diesel::result::Error) -> Self {
+        ImageError::Database(e)
+    }
+}
+
+/// An upload decoded and with its variants encoded, ready to be stored.
+struct PreparedImage {
+    filename: String,
+    raw_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    variants: Vec<EncodedVariant>,
+}
+
+/// The objects of a `PreparedImage` once they are in the storage.
+struct StoredImage {
+    original: UploadedImage,
+    width: u32,
+    height: u32,
+    variants: Vec<(EncodedVariant, UploadedImage)>,
+}
+
+impl StoredImage {
+    fn keys(&self) -> Vec<String> {
+        std::iter::once(self.original.key.clone())
+            .chain(self.variant_keys())
+            .collect()
+    }
+
+    fn variant_keys(&self) -> Vec<String> {
+        self.variants
+            .iter()
+            .map(|(_, uploaded)| uploaded.key.clone())
+            .collect()
+    }
+}
+
+/// A file of a batch upload that could not be saved.
+#[derive(Debug)]
+pub struct FailedFile {
+    /// Position of the file in the batch.
+    pub index: usize,
+    pub file_name: Option<String>,
+    pub error: ImageError,
+}
+
+/// Why `ImageRepository::save_multiple_images` saved nothing. Files not listed in
+/// `Files` were fine but are rolled back with the rest.
+#[derive(Debug)]
+pub enum BatchUploadError {
+    Files(Vec<FailedFile>),
+    Database(diesel::result::Error),
+}
+
+impl fmt::Display for BatchUploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchUploadError::Files(failed) => {
+                write!(f, "{} file(s) could not be saved", failed.len())
+            }
+            BatchUploadError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
 
-use crate::models::image::{Image, NewImage};
-use crate::schema::images;
-use crate::utils::form_fields::ImageFormData;
-use crate::utils::s3_storage::S3Storage;
+impl std::error::Error for BatchUploadError {}
 
 pub struct ImageRepository {
-    s3_storage: S3Storage,
+    storage: Arc<dyn StorageBackend>,
 }
 
 impl ImageRepository {
-    pub async fn new() -> Result<Self, String> {
-        let s3_storage = S3Storage::new().await?;
-        Ok(Self { s3_storage })
+    pub fn new(storage: &Arc<dyn StorageBackend>) -> Self {
+        Self {
+            storage: storage.clone(),
+        }
     }
 
-    pub async fn save_image(
+    pub async fn find(c: &mut AsyncPgConnection, id: i32) -> QueryResult<Image> {
+        images::table.find(id).get_result(c).await
+    }
+
+    pub async fn find_by_key(c: &mut AsyncPgConnection, key: &str) -> QueryResult<Option<Image>> {
+        images::table
+            .filter(images::image_key.eq(key))
+            .first(c)
+            .await
+            .optional()
+    }
+
+    pub async fn find_with_variants(
+        c: &mut AsyncPgConnection,
+        id: i32,
+    ) -> QueryResult<ImageWithVariants> {
+        let image = Self::find(c, id).await?;
+        let mut images = Self::with_variants(c, vec![image]).await?;
+        Ok(images.remove(0))
+    }
+
+    /// Loads the variants of all `images` in one query, keeping their order.
+    pub async fn with_variants(
+        c: &mut AsyncPgConnection,
+        images: Vec<Image>,
+    ) -> QueryResult<Vec<ImageWithVariants>> {
+        let variants = ImageVariant::belonging_to(&images)
+            .order((image_variants::width.asc(), image_variants::format.asc()))
+            .load::<ImageVariant>(c)
+            .await?;
+
+        Ok(variants
+            .grouped_by(&images)
+            .into_iter()
+            .zip(images)
+            .map(|(variants, image)| ImageWithVariants { image, variants })
+            .collect())
+    }
+
+    /// Returns one page of images, newest first, together with the total count.
+    pub async fn find_page(
+        c: &mut AsyncPgConnection,
+        filters: &ImageFilters,
+    ) -> QueryResult<(Vec<ImageWithVariants>, i64)> {
+        let total = images::table.count().get_result(c).await?;
+
+        let images = images::table
+            .order(images::id.desc())
+            .limit(filters.per_page)
+            .offset((filters.page - 1) * filters.per_page)
+            .load(c)
+            .await?;
+
+        Ok((Self::with_variants(c, images).await?, total))
+    }
+
+    /// Whether any news, program, gallery or user still points at the image.
+    pub async fn is_referenced(c: &mut AsyncPgConnection, id: i32) -> QueryResult<bool> {
+        diesel::select(
+            exists(news::table.filter(news::image_id.eq(id)))
+                .or(exists(programs::table.filter(programs::image_id.eq(id))))
+                .or(exists(
+                    galleries::table.filter(galleries::featured_image_id.eq(id)),
+                ))
+                .or(exists(
+                    gallery_images::table.filter(gallery_images::image_id.eq(id)),
+                ))
+                .or(exists(users::table.filter(users::image_id.eq(id)))),
+        )
+        .get_result(c)
+        .await
+    }
+
+    /// Images created before `created_before` that nothing points at, oldest first.
+    /// Library images are never unreferenced.
+    pub async fn find_unreferenced(
+        c: &mut AsyncPgConnection,
+        created_before: NaiveDateTime,
+    ) -> QueryResult<Vec<Image>> {
+        images::table
+            .filter(images::created_at.lt(created_before))
+            .filter(images::in_library.eq(false))
+            .filter(not(exists(
+                news::table.filter(news::image_id.eq(images::id)),
+            )))
+            .filter(not(exists(
+                programs::table.filter(programs::image_id.eq(images::id)),
+            )))
+            .filter(not(exists(
+                galleries::table.filter(galleries::featured_image_id.eq(images::id)),
+            )))
+            .filter(not(exists(
+                gallery_images::table.filter(gallery_images::image_id.eq(images::id)),
+            )))
+            .filter(not(exists(
+                users::table.filter(users::image_id.eq(images::id.nullable())),
+            )))
+            .order(images::id.asc())
+            .load(c)
+            .await
+    }
+
+    /// Keys of every original and variant that has a row.
+    pub async fn find_all_keys(c: &mut AsyncPgConnection) -> QueryResult<HashSet<String>> {
+        let mut keys: HashSet<String> = images::table
+            .select(images::image_key)
+            .load::<String>(c)
+            .await?
+            .into_iter()
+            .collect();
+        keys.extend(
+            image_variants::table
+                .select(image_variants::image_key)
+                .load::<String>(c)
+                .await?,
+        );
+
+        Ok(keys)
+    }
+
+    /// Deletes the row and its variants unless something points at the image again or
+    /// it is in the library, locking it so nothing can start to while we check. Returns the keys of the
+    /// objects, which the caller deletes once the transaction has committed, or `None`
+    /// if the image was kept.
+    pub async fn delete_unreferenced_row(
+        c: &mut AsyncPgConnection,
+        id: i32,
+    ) -> QueryResult<Option<Vec<String>>> {
+        c.transaction(|conn| {
+            Box::pin(async move {
+                let locked = images::table
+                    .find(id)
+                    .filter(images::in_library.eq(false))
+                    .select(images::id)
+                    .for_update()
+                    .first::<i32>(conn)
+                    .await
+                    .optional()?;
+                if locked.is_none() || Self::is_referenced(conn, id).await? {
+                    return Ok(None);
+                }
+
+                Self::delete_row(conn, id).await.map(Some)
+            })
+        })
+        .await
+    }
+
+    /// Stores the image with its variants and inserts their rows. If the insert fails,
+    /// the uploaded objects are deleted again.
+    /// Saves an image to the library, see `Image::in_library`.
+    pub async fn save_library_image(
         &self,
         c: &mut AsyncPgConnection,
         image_field: ImageFormData,
-    ) -> QueryResult<Image> {
-        let (width, height) = Self::get_image_dimensions(&image_field.raw_data)
-            .await
-            .expect("Failed to get image dimensions");
+    ) -> Result<Image, ImageError> {
+        let (image, _) = self.save_image_with_keys(c, image_field, true).await?;
+        Ok(image)
+    }
 
-        let filename = image_field
-            .file_name
-            .unwrap_or_else(|| format!("image_{}.jpg", chrono::Utc::now().timestamp()));
+    async fn save_image_with_keys(
+        &self,
+        c: &mut AsyncPgConnection,
+        image_field: ImageFormData,
+        in_library: bool,
+    ) -> Result<(Image, Vec<String>), ImageError> {
+        let prepared = Self::prepare(image_field).await?;
+        let stored = Self::store(&self.storage, prepared).await?;
+        let keys = stored.keys();
 
-        let uploaded = self
-            .s3_storage
-            .upload_image(&image_field.raw_data, &filename)
-            .await
-            .expect("Failed to upload image to s3");
+        match Self::insert_stored(c, vec![stored], in_library).await {
+            Ok(mut images) => Ok((images.remove(0), keys)),
+            Err(e) => {
+                discard_uploads(&self.storage, keys).await;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Registers an object a client uploaded directly under `key` as a library image,
+    /// see `StorageBackend::presign_upload`. `size` is what `StorageBackend::size` reported,
+    /// so a presigned upload of any size can be refused without downloading it. Objects
+    /// that are too large or not images are deleted; if only storing the variants or the
+    /// insert fails, the object is kept so the client can retry.
+    pub async fn save_uploaded(
+        &self,
+        c: &mut AsyncPgConnection,
+        key: &str,
+        size: u64,
+    ) -> Result<Image, ImageError> {
+        let prepared = if size > MAX_IMAGE_SIZE {
+            Err(ImageError::TooLarge)
+        } else {
+            match self.storage.download(key).await {
+                // The object may have been replaced since its size was checked
+                Ok(raw_data) if raw_data.len() as u64 > MAX_IMAGE_SIZE => Err(ImageError::TooLarge),
+                Ok(raw_data) => {
+                    Self::prepare(ImageFormData {
+                        raw_data,
+                        file_name: Some(key.to_string()),
+                        content_type: None,
+                    })
+                    .await
+                }
+                Err(e) => return Err(ImageError::Storage(e)),
+            }
+        };
+        let prepared = match prepared {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                discard_uploads(&self.storage, vec![key.to_string()]).await;
+                return Err(e);
+            }
+        };
 
-        let new_image = NewImage {
-            image_url: uploaded.url,
-            image_key: uploaded.key,
-            width: width as i32,
-            height: height as i32,
+        let original = UploadedImage {
+            url: self.storage.url_for(key),
+            key: key.to_string(),
         };
+        let stored = Self::store_variants(&self.storage, original, prepared).await?;
+        let variant_keys = stored.variant_keys();
 
-        diesel::insert_into(images::table)
-            .values(new_image)
-            .get_result(c)
-            .await
+        match Self::insert_stored(c, vec![stored], true).await {
+            Ok(mut images) => Ok(images.remove(0)),
+            Err(e) => {
+                discard_uploads(&self.storage, variant_keys).await;
+                Err(e.into())
+            }
+        }
     }
 
+    /// Saves every file or none of them. Files are decoded before anything is uploaded,
+    /// uploaded concurrently, and inserted in one transaction; on any failure the objects
+    /// already uploaded are deleted again. Returns the images in the order of `files`.
     pub async fn save_multiple_images(
         &self,
-        images: &[ImageFormData],
-    ) -> Result<Vec<Image>, String> {
-        // Convert ImageFormData to the format expected by S3Storage
-        let uploads: Vec<(&[u8], String)> = images
-            .iter()
-            .map(|image| {
-                let filename = image
-                    .file_name
-                    .clone()
-                    .unwrap_or_else(|| format!("image_{}.jpg", chrono::Utc::now().timestamp()));
-                (&image.raw_data[..], filename)
+        c: &mut AsyncPgConnection,
+        files: Vec<ImageFormData>,
+    ) -> Result<Vec<Image>, BatchUploadError> {
+        let (images, _) = self.save_multiple_images_with_keys(c, files).await?;
+        Ok(images)
+    }
+
+    async fn save_multiple_images_with_keys(
+        &self,
+        c: &mut AsyncPgConnection,
+        files: Vec<ImageFormData>,
+    ) -> Result<(Vec<Image>, Vec<String>), BatchUploadError> {
+        let file_names: Vec<Option<String>> =
+            files.iter().map(|file| file.file_name.clone()).collect();
+        let prepared = Self::prepare_all(files).await?;
+
+        let handles: Vec<_> = prepared
+            .into_iter()
+            .map(|prepared| {
+                let storage = self.storage.clone();
+                tokio::spawn(async move { Self::store(&storage, prepared).await })
             })
             .collect();
 
-        // Upload all images to S3 concurrently
-        let upload_result = self.s3_storage.upload_multiple_images(uploads).await;
+        let mut stored = Vec::with_capacity(handles.len());
+        let mut failed = Vec::new();
+        for (index, handle) in handles.into_iter().enumerate() {
+            let result = match handle.await {
+                Ok(result) => result,
+                Err(e) => Err(ImageError::Storage(format!("Task failed: {}", e))),
+            };
+            match result {
+                Ok(image) => stored.push(image),
+                Err(error) => failed.push(FailedFile {
+                    index,
+                    file_name: file_names[index].clone(),
+                    error,
+                }),
+            }
+        }
 
-        println!(
-            "Successfully uploaded {} files",
-            upload_result.successful.len()
-        );
-        if !upload_result.failed.is_empty() {
-            println!("Failed to upload {} files", upload_result.failed.len());
-            for (filename, error) in &upload_result.failed {
-                println!("Failed to upload {}: {}", filename, error);
+        let keys: Vec<String> = stored.iter().flat_map(StoredImage::keys).collect();
+
+        if !failed.is_empty() {
+            discard_uploads(&self.storage, keys).await;
+            return Err(BatchUploadError::Files(failed));
+        }
+
+        match Self::insert_stored(c, stored, false).await {
+            Ok(images) => Ok((images, keys)),
+            Err(e) => {
+                discard_uploads(&self.storage, keys).await;
+                Err(BatchUploadError::Database(e))
             }
         }
+    }
 
-        // Mock saving to database - just create Image structs
-        let mock_images: Vec<Image> = upload_result
-            .successful
-            .into_iter()
-            .map(|uploaded| Image {
-                id: rand::random::<i32>(), // Mock ID
-                image_url: uploaded.url,
-                image_key: uploaded.key,
-                width: 800, // Mock dimensions
-                height: 600,
-                created_at: chrono::Utc::now().naive_utc(),
-                updated_at: chrono::Utc::now().naive_utc(),
+    /// Deletes the row, its variants with it, and returns the keys of their objects. The
+    /// objects are left for the caller, see `ImageChanges`.
+    pub async fn delete_row(c: &mut AsyncPgConnection, id: i32) -> QueryResult<Vec<String>> {
+        let image = images::table.find(id).first::<Image>(c).await?;
+        let variant_keys: Vec<String> = image_variants::table
+            .filter(image_variants::image_id.eq(id))
+            .select(image_variants::image_key)
+            .load(c)
+            .await?;
+
+        diesel::delete(images::table.find(id)).execute(c).await?;
+
+        Ok(std::iter::once(image.image_key)
+            .chain(variant_keys)
+            .collect())
+    }
+
+    /// Decodes the upload and encodes its variants on a blocking thread.
+    async fn prepare(image_field: ImageFormData) -> Result<PreparedImage, ImageError> {
+        tokio::task::spawn_blocking(move || {
+            let img = image::load_from_memory(&image_field.raw_data)
+                .map_err(|e| ImageError::Decode(e.to_string()))?;
+            let variants = generate_variants(&img).map_err(ImageError::Decode)?;
+
+            let filename = image_field
+                .file_name
+                .unwrap_or_else(|| format!("image_{}.jpg", chrono::Utc::now().timestamp()));
+
+            Ok(PreparedImage {
+                filename,
+                raw_data: image_field.raw_data,
+                width: img.width(),
+                height: img.height(),
+                variants,
+            })
+        })
+        .await
+        .map_err(|e| ImageError::Decode(e.to_string()))?
+    }
+
+    /// Prepares every file of a batch, collecting all the files that cannot be decoded.
+    async fn prepare_all(
+        files: Vec<ImageFormData>,
+    ) -> Result<Vec<PreparedImage>, BatchUploadError> {
+        let mut prepared = Vec::with_capacity(files.len());
+        let mut failed = Vec::new();
+
+        for (index, file) in files.into_iter().enumerate() {
+            let file_name = file.file_name.clone();
+            match Self::prepare(file).await {
+                Ok(image) => prepared.push(image),
+                Err(error) => failed.push(FailedFile {
+                    index,
+                    file_name,
+                    error,
+                }),
+            }
+        }
+
+        if failed.is_empty() {
+            Ok(prepared)
+        } else {
+            Err(BatchUploadError::Files(failed))
+        }
+    }
+
+    /// Uploads the original, then its variants. Deletes whatever was uploaded if any of
+    /// them fails.
+    async fn store(
+        storage: &Arc<dyn StorageBackend>,
+        prepared: PreparedImage,
+    ) -> Result<StoredImage, ImageError> {
+        let original = upload_image(storage.as_ref(), &prepared.raw_data, &prepared.filename)
+            .await
+            .map_err(ImageError::Storage)?;
+        let original_key = original.key.clone();
+
+        match Self::store_variants(storage, original, prepared).await {
+            Ok(stored) => Ok(stored),
+            Err(e) => {
+                discard_uploads(storage, vec![original_key]).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Uploads the variants next to the already stored original as
+    /// `<key>-<name>.<extension>`. Deletes the variants uploaded if any of them fails.
+    async fn store_variants(
+        storage: &Arc<dyn StorageBackend>,
+        original: UploadedImage,
+        prepared: PreparedImage,
+    ) -> Result<StoredImage, ImageError> {
+        let base_key = original
+            .key
+            .rsplit_once('.')
+            .map_or(original.key.as_str(), |(base, _)| base);
+        let objects = prepared
+            .variants
+            .iter()
+            .map(|variant| {
+                (
+                    variant.data.clone(),
+                    format!(
+                        "{}-{}.{}",
+                        base_key,
+                        variant.name,
+                        variant.format.extension()
+                    ),
+                    variant.format.content_type(),
+                )
             })
             .collect();
+        let results = upload_objects(storage, objects).await;
 
-        Ok(mock_images)
+        let mut variants = Vec::with_capacity(results.len());
+        let mut error = None;
+        for (variant, result) in prepared.variants.into_iter().zip(results) {
+            match result {
+                Ok(uploaded) => variants.push((variant, uploaded)),
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        let stored = StoredImage {
+            original,
+            width: prepared.width,
+            height: prepared.height,
+            variants,
+        };
+
+        match error {
+            None => Ok(stored),
+            Some(e) => {
+                discard_uploads(storage, stored.variant_keys()).await;
+                Err(ImageError::Storage(e))
+            }
+        }
     }
 
-    pub async fn delete_image(&self, c: &mut AsyncPgConnection, id: i32) -> QueryResult<usize> {
-        // First get the image key from database
-        let image = images::table.find(id).first::<Image>(c).await?;
+    /// Inserts the rows of the stored images and their variants in one transaction, a
+    /// savepoint when the caller already runs one so a failure does not leave theirs aborted.
+    async fn insert_stored(
+        c: &mut AsyncPgConnection,
+        stored: Vec<StoredImage>,
+        in_library: bool,
+    ) -> QueryResult<Vec<Image>> {
+        c.transaction(|conn| {
+            Box::pin(async move {
+                let new_images: Vec<NewImage> = stored
+                    .iter()
+                    .map(|image| NewImage {
+                        image_url: image.original.url.clone(),
+                        image_key: image.original.key.clone(),
+                        width: image.width as i32,
+                        height: image.height as i32,
+                        in_library,
+                    })
+                    .collect();
+
+                let images: Vec<Image> = diesel::insert_into(images::table)
+                    .values(&new_images)
+                    .get_results(conn)
+                    .await?;
+
+                let new_variants: Vec<NewImageVariant> = images
+                    .iter()
+                    .zip(stored)
+                    .flat_map(|(image, stored)| {
+                        let image_id = image.id;
+                        stored.variants.into_iter().map(move |(variant, uploaded)| {
+                            NewImageVariant {
+                                image_id,
+                                name: variant.name.to_string(),
+                                format: variant.format.name().to_string(),
+                                image_url: uploaded.url,
+                                image_key: uploaded.key,
+                                width: variant.width as i32,
+                                height: variant.height as i32,
+                            }
+                        })
+                    })
+                    .collect();
+
+                if !new_variants.is_empty() {
+                    diesel::insert_into(image_variants::table)
+                        .values(&new_variants)
+                        .execute(conn)
+                        .await?;
+                }
+
+                Ok(images)
+            })
+        })
+        .await
+    }
+}
+
+/// Images saved and deleted inside a database transaction. Their objects wait for its
+/// outcome: those of deleted images are deleted once it commits, those of new images
+/// if it rolls back. Borrow it mutably in the transaction and pass what the transaction
+/// returned to `finish`.
+pub struct ImageChanges {
+    repo: ImageRepository,
+    saved_keys: Vec<String>,
+    deleted_keys: Vec<String>,
+}
+
+impl ImageChanges {
+    pub fn new(storage: &Arc<dyn StorageBackend>) -> Self {
+        Self {
+            repo: ImageRepository::new(storage),
+            saved_keys: Vec::new(),
+            deleted_keys: Vec::new(),
+        }
+    }
+
+    pub async fn save_image(
+        &mut self,
+        c: &mut AsyncPgConnection,
+        image_field: ImageFormData,
+    ) -> Result<Image, ImageError> {
+        let (image, keys) = self
+            .repo
+            .save_image_with_keys(c, image_field, false)
+            .await?;
+        self.saved_keys.extend(keys);
+        Ok(image)
+    }
+
+    pub async fn save_multiple_images(
+        &mut self,
+        c: &mut AsyncPgConnection,
+        files: Vec<ImageFormData>,
+    ) -> Result<Vec<Image>, BatchUploadError> {
+        let (images, keys) = self.repo.save_multiple_images_with_keys(c, files).await?;
+        self.saved_keys.extend(keys);
+        Ok(images)
+    }
+
+    /// Deletes the image, failing if anything still points at it.
+    pub async fn delete_image(&mut self, c: &mut AsyncPgConnection, id: i32) -> QueryResult<()> {
+        let keys = ImageRepository::delete_row(c, id).await?;
+        self.deleted_keys.extend(keys);
+        Ok(())
+    }
+
+    /// Deletes the image unless anything still points at it, for when its owner stops
+    /// using it. The image library and JSON bodies let several rows share one image.
+    pub async fn release_image(&mut self, c: &mut AsyncPgConnection, id: i32) -> QueryResult<()> {
+        if let Some(keys) = ImageRepository::delete_unreferenced_row(c, id).await? {
+            self.deleted_keys.extend(keys);
+        }
+        Ok(())
+    }
 
-        // Delete from S3
-        self.s3_storage
-            .delete_image(&image.image_key)
+    /// Deletes the objects the outcome of the transaction left without a row.
+    pub async fn finish<T, E>(self, result: Result<T, E>) -> Result<T, E> {
+        let keys = match result {
+            Ok(_) => self.deleted_keys,
+            Err(_) => self.saved_keys,
+        };
+        discard_uploads(&self.repo.storage, keys).await;
+
+        result
+    }
+}
+
+/// Deletes objects that no row points at. The transaction that made them orphans is
+/// already over, so a failure is logged and left to the image GC rather than returned.
+async fn discard_uploads(storage: &Arc<dyn StorageBackend>, keys: Vec<String>) {
+    if keys.is_empty() {
+        return;
+    }
+    if let Err(e) = delete_objects(storage, keys).await {
+        rocket::warn!("Failed to delete objects without an image row: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use rocket::http::Status;
+
+    use super::*;
+    use crate::errors::ApiError;
+    use crate::utils::storage::{MemoryStorage, StoredObject};
+
+    /// Accepts `uploads_left` uploads, then fails every further one.
+    #[derive(Default)]
+    struct FailingStorage {
+        memory: MemoryStorage,
+        uploads_left: AtomicUsize,
+    }
+
+    #[rocket::async_trait]
+    impl StorageBackend for FailingStorage {
+        async fn upload(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<(), String> {
+            self.uploads_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                    left.checked_sub(1)
+                })
+                .map_err(|_| "storage is down".to_string())?;
+            self.memory.upload(key, data, content_type).await
+        }
+
+        async fn delete(&self, key: &str) -> Result<(), String> {
+            self.memory.delete(key).await
+        }
+
+        fn url_for(&self, key: &str) -> String {
+            self.memory.url_for(key)
+        }
+
+        async fn size(&self, key: &str) -> Result<Option<u64>, String> {
+            self.memory.size(key).await
+        }
+
+        async fn download(&self, key: &str) -> Result<Vec<u8>, String> {
+            self.memory.download(key).await
+        }
+
+        async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, String> {
+            self.memory.list(prefix).await
+        }
+    }
+
+    fn file(raw_data: &[u8], name: &str) -> ImageFormData {
+        ImageFormData {
+            raw_data: raw_data.to_vec(),
+            file_name: Some(name.to_string()),
+            content_type: None,
+        }
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(width, height)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        png.into_inner()
+    }
+
+    #[rocket::async_test]
+    async fn test_undecodable_image_is_rejected() {
+        let result = ImageRepository::prepare(file(b"not an image", "a.jpg")).await;
+        assert!(matches!(result, Err(ImageError::Decode(_))));
+    }
+
+    #[rocket::async_test]
+    async fn test_prepare_reads_dimensions_and_variants() {
+        let prepared = ImageRepository::prepare(file(&png(400, 200), "a.png"))
             .await
-            .expect("Failed to delete image from S3");
+            .unwrap();
 
-        // Delete from database
-        diesel::delete(images::table.find(id)).execute(c).await
+        assert_eq!((prepared.width, prepared.height), (400, 200));
+        assert_eq!(prepared.filename, "a.png");
+        assert_eq!(prepared.variants.len(), 2);
     }
 
-    async fn get_image_dimensions(raw_data: &[u8]) -> Result<(u32, u32), String> {
-        let img =
-            image::load_from_memory(raw_data).map_err(|e| format!("Cannot decode image: {}", e))?;
-        Ok((img.width(), img.height()))
+    #[rocket::async_test]
+    async fn test_storage_failure_is_a_bad_gateway() {
+        let storage: Arc<dyn StorageBackend> = Arc::new(FailingStorage::default());
+        let prepared = ImageRepository::prepare(file(&png(400, 200), "a.png"))
+            .await
+            .unwrap();
+
+        match ImageRepository::store(&storage, prepared).await {
+            Err(error @ ImageError::Storage(_)) => {
+                assert_eq!(ApiError::from(error).status(), Status::BadGateway);
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+            Ok(_) => panic!("the upload did not fail"),
+        }
+    }
+
+    #[rocket::async_test]
+    async fn test_failed_variant_discards_the_original() {
+        let storage: Arc<dyn StorageBackend> = Arc::new(FailingStorage {
+            uploads_left: AtomicUsize::new(1),
+            ..Default::default()
+        });
+        let prepared = ImageRepository::prepare(file(&png(400, 200), "a.png"))
+            .await
+            .unwrap();
+
+        let result = ImageRepository::store(&storage, prepared).await;
+
+        assert!(matches!(result, Err(ImageError::Storage(_))));
+        assert!(storage.list("uploads/").await.unwrap().is_empty());
+    }
+
+    #[rocket::async_test]
+    async fn test_changes_wait_for_the_transaction() {
+        let memory = Arc::new(MemoryStorage::default());
+        let storage: Arc<dyn StorageBackend> = memory.clone();
+        for key in ["uploads/new.jpg", "uploads/old.jpg"] {
+            storage.upload(key, vec![1], "image/jpeg").await.unwrap();
+        }
+        let changes = || {
+            let mut changes = ImageChanges::new(&storage);
+            changes.saved_keys.push("uploads/new.jpg".to_string());
+            changes.deleted_keys.push("uploads/old.jpg".to_string());
+            changes
+        };
+
+        let rolled_back: Result<(), &str> = changes().finish(Err("rollback")).await;
+        assert!(rolled_back.is_err());
+        assert!(memory.get("uploads/new.jpg").is_none());
+        assert!(memory.get("uploads/old.jpg").is_some());
+
+        changes().finish(Ok::<_, ()>(())).await.unwrap();
+        assert!(memory.get("uploads/old.jpg").is_none());
+    }
+
+    #[rocket::async_test]
+    async fn test_batch_reports_every_undecodable_file() {
+        let files = vec![
+            file(&png(3, 2), "a.png"),
+            file(b"junk", "b.png"),
+            file(b"", "c.png"),
+        ];
+
+        match ImageRepository::prepare_all(files).await {
+            Err(BatchUploadError::Files(failed)) => {
+                let indexes: Vec<_> = failed.iter().map(|f| f.index).collect();
+                assert_eq!(indexes, vec![1, 2]);
+                assert!(matches!(failed[0].error, ImageError::Decode(_)));
+            }
+            Err(e) => panic!("unexpected error: {:?}", e),
+            Ok(_) => panic!("undecodable files were accepted"),
+        }
     }
 }
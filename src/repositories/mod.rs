@@ -2,4 +2,7 @@ pub mod event;
 pub mod gallery;
 pub mod image;
 pub mod news;
+pub mod one_time_token;
 pub mod program;
+pub mod session;
+pub mod user;
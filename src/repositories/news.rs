@@ -22,7 +22,12 @@ impl NewsRepository {
             .await
     }
 
+    /// An update without any field returns the news unchanged.
     pub async fn update(c: &mut AsyncPgConnection, id: i32, news: UpdateNews) -> QueryResult<News> {
+        if news.is_empty() {
+            return Self::find(c, id).await;
+        }
+
         diesel::update(news::table.find(id))
             .set(&news)
             .get_result(c)
@@ -0,0 +1,75 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::models::one_time_token::OneTimeToken;
+use crate::schema::{email_verification_tokens, password_reset_tokens};
+
+/// Defines a repository for a table of hashed, expiring, single-use tokens. The
+/// tables only differ in name and token lifetime, so the expiry and single-use
+/// rules live here once.
+macro_rules! one_time_token_repository {
+    ($(#[$meta:meta])* $name:ident, $table:ident, $ttl:expr) => {
+        $(#[$meta])*
+        pub struct $name;
+
+        impl $name {
+            /// Stores a new token for the user, discarding any earlier unused ones.
+            pub async fn create(
+                c: &mut AsyncPgConnection,
+                user_id: i32,
+                token_hash: String,
+            ) -> QueryResult<OneTimeToken> {
+                diesel::delete(
+                    $table::table
+                        .filter($table::user_id.eq(user_id))
+                        .filter($table::used_at.is_null()),
+                )
+                .execute(c)
+                .await?;
+
+                diesel::insert_into($table::table)
+                    .values((
+                        $table::user_id.eq(user_id),
+                        $table::token_hash.eq(token_hash),
+                        $table::expires_at.eq((Utc::now() + $ttl).naive_utc()),
+                    ))
+                    .get_result(c)
+                    .await
+            }
+
+            /// Marks an unused, unexpired token as used and returns it. Fails with
+            /// `NotFound` for unknown, expired or already used tokens.
+            pub async fn consume(
+                c: &mut AsyncPgConnection,
+                token_hash: &str,
+            ) -> QueryResult<OneTimeToken> {
+                let now = Utc::now().naive_utc();
+
+                diesel::update(
+                    $table::table
+                        .filter($table::token_hash.eq(token_hash))
+                        .filter($table::used_at.is_null())
+                        .filter($table::expires_at.gt(now)),
+                )
+                .set($table::used_at.eq(now))
+                .get_result(c)
+                .await
+            }
+        }
+    };
+}
+
+one_time_token_repository!(
+    /// Tokens mailed to confirm an email address, valid for 48 hours.
+    EmailVerificationTokenRepository,
+    email_verification_tokens,
+    Duration::hours(48)
+);
+
+one_time_token_repository!(
+    /// Tokens mailed to reset a forgotten password, valid for an hour.
+    PasswordResetTokenRepository,
+    password_reset_tokens,
+    Duration::minutes(60)
+);
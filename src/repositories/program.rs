@@ -31,11 +31,16 @@ impl ProgramRepository {
             .await
     }
 
+    /// An update without any field returns the program unchanged.
     pub async fn update(
         c: &mut AsyncPgConnection,
         id: i32,
         program: UpdateProgram,
     ) -> QueryResult<Program> {
+        if program.is_empty() {
+            return Self::find(c, id).await;
+        }
+
         diesel::update(programs::table.find(id))
             .set(&program)
             .get_result(c)
@@ -0,0 +1,110 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::models::session::{NewSession, Session};
+use crate::schema::sessions;
+
+const SESSION_TTL_DAYS: i64 = 30;
+
+pub struct SessionRepository;
+
+impl SessionRepository {
+    pub async fn create(
+        c: &mut AsyncPgConnection,
+        user_id: i32,
+        refresh_token_hash: String,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> QueryResult<Session> {
+        let new_session = NewSession {
+            user_id,
+            refresh_token_hash,
+            user_agent,
+            ip_address,
+            expires_at: (Utc::now() + Duration::days(SESSION_TTL_DAYS)).naive_utc(),
+        };
+
+        diesel::insert_into(sessions::table)
+            .values(new_session)
+            .get_result(c)
+            .await
+    }
+
+    /// Finds a session of the user that is neither revoked nor expired.
+    pub async fn find_active(
+        c: &mut AsyncPgConnection,
+        id: i32,
+        user_id: i32,
+    ) -> QueryResult<Session> {
+        sessions::table
+            .find(id)
+            .filter(sessions::user_id.eq(user_id))
+            .filter(sessions::revoked_at.is_null())
+            .filter(sessions::expires_at.gt(Utc::now().naive_utc()))
+            .first(c)
+            .await
+    }
+
+    pub async fn find_active_for_user(
+        c: &mut AsyncPgConnection,
+        user_id: i32,
+    ) -> QueryResult<Vec<Session>> {
+        sessions::table
+            .filter(sessions::user_id.eq(user_id))
+            .filter(sessions::revoked_at.is_null())
+            .filter(sessions::expires_at.gt(Utc::now().naive_utc()))
+            .order(sessions::last_used_at.desc())
+            .load(c)
+            .await
+    }
+
+    /// Swaps the refresh token of an active session for a new one, so every refresh
+    /// token works only once. Fails with `NotFound` for unknown or used tokens.
+    pub async fn rotate(
+        c: &mut AsyncPgConnection,
+        refresh_token_hash: &str,
+        new_refresh_token_hash: String,
+    ) -> QueryResult<Session> {
+        let now = Utc::now().naive_utc();
+
+        diesel::update(
+            sessions::table
+                .filter(sessions::refresh_token_hash.eq(refresh_token_hash))
+                .filter(sessions::revoked_at.is_null())
+                .filter(sessions::expires_at.gt(now)),
+        )
+        .set((
+            sessions::refresh_token_hash.eq(new_refresh_token_hash),
+            sessions::last_used_at.eq(now),
+        ))
+        .get_result(c)
+        .await
+    }
+
+    pub async fn revoke(c: &mut AsyncPgConnection, id: i32, user_id: i32) -> QueryResult<usize> {
+        diesel::update(
+            sessions::table
+                .find(id)
+                .filter(sessions::user_id.eq(user_id))
+                .filter(sessions::revoked_at.is_null()),
+        )
+        .set(sessions::revoked_at.eq(Utc::now().naive_utc()))
+        .execute(c)
+        .await
+    }
+
+    pub async fn revoke_all_for_user(
+        c: &mut AsyncPgConnection,
+        user_id: i32,
+    ) -> QueryResult<usize> {
+        diesel::update(
+            sessions::table
+                .filter(sessions::user_id.eq(user_id))
+                .filter(sessions::revoked_at.is_null()),
+        )
+        .set(sessions::revoked_at.eq(Utc::now().naive_utc()))
+        .execute(c)
+        .await
+    }
+}
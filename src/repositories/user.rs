@@ -0,0 +1,262 @@
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+use crate::models::user::{
+    normalize_email, Credentials, NewUser, Participant, RegisterUser, RequestStatus, UpdateProfile,
+    User, UserFilters, UserRole, UserType,
+};
+use crate::schema::{images, users};
+use crate::utils::password_hashing::Password;
+
+sql_function!(fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text);
+
+pub struct UserRepository;
+
+impl UserRepository {
+    pub async fn find(c: &mut AsyncPgConnection, id: i32) -> QueryResult<User> {
+        users::table.find(id).get_result(c).await
+    }
+
+    /// Ignores case, as accounts registered before emails were normalized may have
+    /// mixed-case addresses.
+    pub async fn find_by_email(c: &mut AsyncPgConnection, email: &str) -> QueryResult<User> {
+        users::table
+            .filter(lower(users::email).eq(normalize_email(email)))
+            .first(c)
+            .await
+    }
+
+    /// Returns one page of users matching the filters together with the total match count.
+    pub async fn find_filtered(
+        c: &mut AsyncPgConnection,
+        filters: &UserFilters,
+    ) -> QueryResult<(Vec<User>, i64)> {
+        let total = Self::filtered(filters).count().get_result(c).await?;
+
+        let users = Self::filtered(filters)
+            .order(users::id.asc())
+            .limit(filters.per_page)
+            .offset((filters.page - 1) * filters.per_page)
+            .load(c)
+            .await?;
+
+        Ok((users, total))
+    }
+
+    fn filtered(filters: &UserFilters) -> users::BoxedQuery<'_, Pg> {
+        let mut query = users::table.into_boxed();
+
+        if let Some(role) = filters.role {
+            query = query.filter(users::role.eq(role));
+        }
+        if let Some(user_type) = filters.user_type {
+            query = query.filter(users::user_type.eq(user_type));
+        }
+        if let Some(request) = filters.request {
+            query = query.filter(users::request.eq(request));
+        }
+        if let Some(event_id) = filters.event_id {
+            query = query.filter(users::event_id.eq(event_id));
+        }
+        if let Some(search) = &filters.search {
+            let pattern = format!(
+                "%{}%",
+                search
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+            );
+            query = query.filter(
+                users::username
+                    .ilike(pattern.clone())
+                    .or(users::email.ilike(pattern)),
+            );
+        }
+
+        query
+    }
+
+    pub async fn create(
+        c: &mut AsyncPgConnection,
+        registration: RegisterUser,
+        role: UserRole,
+    ) -> QueryResult<User> {
+        let new_user = NewUser {
+            username: registration.username,
+            email: normalize_email(&registration.email),
+            password: Password::generate_password(&registration.password),
+            phone_number: registration.phone_number,
+            description: registration.description,
+            user_type: registration.user_type,
+            role,
+            event_id: None,
+            request: RequestStatus::NotSend,
+            image_id: None,
+        };
+
+        diesel::insert_into(users::table)
+            .values(new_user)
+            .get_result(c)
+            .await
+    }
+
+    /// An update without any field returns the user unchanged.
+    pub async fn update_profile(
+        c: &mut AsyncPgConnection,
+        id: i32,
+        profile: UpdateProfile,
+    ) -> QueryResult<User> {
+        if profile.is_empty() {
+            return Self::find(c, id).await;
+        }
+
+        diesel::update(users::table.find(id))
+            .set(&profile)
+            .get_result(c)
+            .await
+    }
+
+    pub async fn find_by_request(
+        c: &mut AsyncPgConnection,
+        request: RequestStatus,
+    ) -> QueryResult<Vec<User>> {
+        users::table
+            .filter(users::request.eq(request))
+            .order(users::updated_at.asc())
+            .load(c)
+            .await
+    }
+
+    pub async fn update_role(
+        c: &mut AsyncPgConnection,
+        id: i32,
+        role: UserRole,
+    ) -> QueryResult<User> {
+        diesel::update(users::table.find(id))
+            .set(users::role.eq(role))
+            .get_result(c)
+            .await
+    }
+
+    pub async fn set_disabled(
+        c: &mut AsyncPgConnection,
+        id: i32,
+        disabled: bool,
+    ) -> QueryResult<User> {
+        let disabled_at = disabled.then(|| chrono::Utc::now().naive_utc());
+
+        diesel::update(users::table.find(id))
+            .set(users::disabled_at.eq(disabled_at))
+            .get_result(c)
+            .await
+    }
+
+    pub async fn set_locked_until(
+        c: &mut AsyncPgConnection,
+        id: i32,
+        locked_until: Option<chrono::NaiveDateTime>,
+    ) -> QueryResult<User> {
+        diesel::update(users::table.find(id))
+            .set(users::locked_until.eq(locked_until))
+            .get_result(c)
+            .await
+    }
+
+    pub async fn mark_email_verified(c: &mut AsyncPgConnection, id: i32) -> QueryResult<User> {
+        diesel::update(users::table.find(id))
+            .set(users::email_verified_at.eq(chrono::Utc::now().naive_utc()))
+            .get_result(c)
+            .await
+    }
+
+    pub async fn delete(c: &mut AsyncPgConnection, id: i32) -> QueryResult<usize> {
+        diesel::delete(users::table.find(id)).execute(c).await
+    }
+
+    pub async fn update_password(
+        c: &mut AsyncPgConnection,
+        id: i32,
+        password: &str,
+    ) -> QueryResult<User> {
+        diesel::update(users::table.find(id))
+            .set(users::password.eq(Password::generate_password(password)))
+            .get_result(c)
+            .await
+    }
+
+    pub async fn find_participants(
+        c: &mut AsyncPgConnection,
+        event_id: i32,
+        user_type: Option<UserType>,
+    ) -> QueryResult<Vec<Participant>> {
+        let mut query = users::table
+            .left_join(images::table)
+            .filter(users::event_id.eq(event_id))
+            .filter(users::request.eq(RequestStatus::Confirmed))
+            .select((
+                users::username,
+                users::description,
+                users::user_type,
+                images::image_url.nullable(),
+            ))
+            .order(users::username.asc())
+            .into_boxed();
+
+        if let Some(user_type) = user_type {
+            query = query.filter(users::user_type.eq(user_type));
+        }
+
+        query.load(c).await
+    }
+
+    /// Moves `user`'s application to `to` for `event_id`, but only if the row still holds
+    /// the status and event that were read, so concurrent reviews cannot skip a step.
+    /// Returns `NotFound` when the row changed in the meantime.
+    pub async fn update_request(
+        c: &mut AsyncPgConnection,
+        user: &User,
+        event_id: i32,
+        to: RequestStatus,
+    ) -> QueryResult<User> {
+        diesel::update(
+            users::table
+                .find(user.id)
+                .filter(users::request.eq(user.request))
+                .filter(users::event_id.is_not_distinct_from(user.event_id)),
+        )
+        .set((users::request.eq(to), users::event_id.eq(event_id)))
+        .get_result(c)
+        .await
+    }
+
+    /// Returns `None` when the email is unknown or the password does not match.
+    /// Hashes created with outdated parameters are upgraded on a successful login.
+    pub async fn authenticate(
+        c: &mut AsyncPgConnection,
+        credentials: &Credentials,
+    ) -> QueryResult<Option<User>> {
+        let user = match Self::find_by_email(c, &credentials.email).await {
+            Ok(user) => user,
+            Err(diesel::result::Error::NotFound) => {
+                // Take as long as a wrong password, so the timing does not tell which
+                // emails have an account
+                Password::compare_dummy(&credentials.password);
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+
+        if !Password::compare(&user.password, &credentials.password) {
+            return Ok(None);
+        }
+
+        if Password::needs_rehash(&user.password) {
+            return Self::update_password(c, user.id, &credentials.password)
+                .await
+                .map(Some);
+        }
+
+        Ok(Some(user))
+    }
+}
@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use crate::errors::ApiError;
+use crate::models::user::{UpdateRole, UserFilters};
+use crate::repositories::image::ImageChanges;
+use crate::repositories::session::SessionRepository;
+use crate::repositories::user::UserRepository;
+use crate::utils::rate_limiter::LoginRateLimiter;
+use crate::utils::storage::StorageBackend;
+
+use diesel::result::Error;
+use rocket::response::status::NoContent;
+use rocket::serde::json::{json, Json, Value};
+use rocket::State;
+use rocket_db_pools::Connection;
+
+use super::{AdminUser, DbConn};
+
+fn refuse_self(admin: &AdminUser, id: i32) -> Result<(), ApiError> {
+    if admin.0.id == id {
+        return Err(ApiError::Conflict(
+            "Admins cannot do this to their own account".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[rocket::get("/admin/users?<filters..>")]
+pub async fn get_users(
+    mut db: Connection<DbConn>,
+    _admin: AdminUser,
+    filters: UserFilters,
+) -> Result<Value, ApiError> {
+    UserRepository::find_filtered(&mut db, &filters)
+        .await
+        .map(|(users, total)| {
+            json!({
+                "users": users,
+                "page": filters.page,
+                "per_page": filters.per_page,
+                "total": total,
+            })
+        })
+        .map_err(ApiError::from)
+}
+
+#[rocket::put("/admin/users/<id>/role", format = "json", data = "<update>")]
+pub async fn update_user_role(
+    mut db: Connection<DbConn>,
+    admin: AdminUser,
+    id: i32,
+    update: Json<UpdateRole>,
+) -> Result<Value, ApiError> {
+    refuse_self(&admin, id)?;
+
+    UserRepository::update_role(&mut db, id, update.role)
+        .await
+        .map(|user| json!(user))
+        .map_err(ApiError::from)
+}
+
+#[rocket::put("/admin/users/<id>/disable")]
+pub async fn disable_user(
+    mut db: Connection<DbConn>,
+    admin: AdminUser,
+    id: i32,
+) -> Result<Value, ApiError> {
+    refuse_self(&admin, id)?;
+
+    db.build_transaction()
+        .run(|conn| {
+            Box::pin(async move {
+                let user = UserRepository::set_disabled(conn, id, true).await?;
+                SessionRepository::revoke_all_for_user(conn, id).await?;
+                Ok::<_, Error>(user)
+            })
+        })
+        .await
+        .map(|user| json!(user))
+        .map_err(ApiError::from)
+}
+
+#[rocket::put("/admin/users/<id>/enable")]
+pub async fn enable_user(
+    mut db: Connection<DbConn>,
+    _admin: AdminUser,
+    id: i32,
+) -> Result<Value, ApiError> {
+    UserRepository::set_disabled(&mut db, id, false)
+        .await
+        .map(|user| json!(user))
+        .map_err(ApiError::from)
+}
+
+#[rocket::put("/admin/users/<id>/unlock")]
+pub async fn unlock_user(
+    mut db: Connection<DbConn>,
+    limiter: &State<LoginRateLimiter>,
+    _admin: AdminUser,
+    id: i32,
+) -> Result<Value, ApiError> {
+    let user = UserRepository::set_locked_until(&mut db, id, None).await?;
+    limiter.unlock(&user.email).await;
+
+    Ok(json!(user))
+}
+
+#[rocket::get("/admin/users/<id>/sessions")]
+pub async fn get_user_sessions(
+    mut db: Connection<DbConn>,
+    _admin: AdminUser,
+    id: i32,
+) -> Result<Value, ApiError> {
+    SessionRepository::find_active_for_user(&mut db, id)
+        .await
+        .map(|sessions| json!(sessions))
+        .map_err(ApiError::from)
+}
+
+#[rocket::delete("/admin/users/<id>/sessions")]
+pub async fn revoke_user_sessions(
+    mut db: Connection<DbConn>,
+    _admin: AdminUser,
+    id: i32,
+) -> Result<NoContent, ApiError> {
+    SessionRepository::revoke_all_for_user(&mut db, id)
+        .await
+        .map(|_| NoContent)
+        .map_err(ApiError::from)
+}
+
+#[rocket::delete("/admin/users/<id>/sessions/<session_id>")]
+pub async fn revoke_user_session(
+    mut db: Connection<DbConn>,
+    _admin: AdminUser,
+    id: i32,
+    session_id: i32,
+) -> Result<NoContent, ApiError> {
+    match SessionRepository::revoke(&mut db, session_id, id).await {
+        Ok(0) => Err(ApiError::NotFound("Session not found".to_string())),
+        Ok(_) => Ok(NoContent),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[rocket::delete("/admin/users/<id>")]
+pub async fn delete_user(
+    mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    admin: AdminUser,
+    id: i32,
+) -> Result<NoContent, ApiError> {
+    refuse_self(&admin, id)?;
+
+    let mut changes = ImageChanges::new(storage);
+
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                let user = UserRepository::find(conn, id).await?;
+
+                UserRepository::delete(conn, id).await?;
+
+                if let Some(image_id) = user.image_id {
+                    changes.release_image(conn, image_id).await?;
+                }
+
+                Ok::<_, ApiError>(())
+            })
+        })
+        .await;
+
+    changes.finish(result).await.map(|_| NoContent)
+}
@@ -0,0 +1,108 @@
+use crate::errors::ApiError;
+use crate::models::user::{RequestStatus, User};
+use crate::repositories::event::EventRepository;
+use crate::repositories::user::UserRepository;
+
+use diesel::result::Error;
+use diesel_async::AsyncPgConnection;
+use rocket::serde::json::{json, Value};
+use rocket_db_pools::Connection;
+
+use super::{AdminUser, AuthenticatedUser, DbConn};
+
+fn invalid_transition(from: RequestStatus, to: RequestStatus) -> ApiError {
+    ApiError::Conflict(format!("Cannot change application from {} to {}", from, to))
+}
+
+async fn transition(
+    c: &mut AsyncPgConnection,
+    user: &User,
+    event_id: i32,
+    to: RequestStatus,
+) -> Result<User, ApiError> {
+    // An application for a past event does not block applying to the current one.
+    let from = if user.event_id == Some(event_id) {
+        user.request
+    } else {
+        RequestStatus::NotSend
+    };
+
+    if !from.can_transition_to(to) {
+        return Err(invalid_transition(from, to));
+    }
+
+    UserRepository::update_request(c, user, event_id, to)
+        .await
+        .map_err(|e| match e {
+            Error::NotFound => invalid_transition(from, to),
+            _ => e.into(),
+        })
+}
+
+async fn review(
+    c: &mut AsyncPgConnection,
+    user_id: i32,
+    to: RequestStatus,
+) -> Result<Value, ApiError> {
+    let user = UserRepository::find(c, user_id).await?;
+
+    let event_id = user
+        .event_id
+        .ok_or_else(|| invalid_transition(user.request, to))?;
+
+    transition(c, &user, event_id, to)
+        .await
+        .map(|user| json!(user))
+}
+
+#[rocket::post("/applications")]
+pub async fn submit_application(
+    mut db: Connection<DbConn>,
+    user: AuthenticatedUser,
+) -> Result<Value, ApiError> {
+    if user.0.email_verified_at.is_none() {
+        return Err(ApiError::Forbidden(
+            "Verify your email address before applying".to_string(),
+        ));
+    }
+
+    let event = EventRepository::find_current(&mut db)
+        .await
+        .map_err(|e| match e {
+            Error::NotFound => ApiError::NotFound("There is no current event".to_string()),
+            _ => e.into(),
+        })?;
+
+    transition(&mut db, &user.0, event.id, RequestStatus::Pending)
+        .await
+        .map(|user| json!(user))
+}
+
+#[rocket::get("/applications/pending")]
+pub async fn get_pending_applications(
+    mut db: Connection<DbConn>,
+    _admin: AdminUser,
+) -> Result<Value, ApiError> {
+    UserRepository::find_by_request(&mut db, RequestStatus::Pending)
+        .await
+        .map(|users| json!(users))
+        .map_err(ApiError::from)
+}
+
+#[rocket::put("/applications/<user_id>/confirm")]
+pub async fn confirm_application(
+    mut db: Connection<DbConn>,
+    _admin: AdminUser,
+    user_id: i32,
+) -> Result<Value, ApiError> {
+    review(&mut db, user_id, RequestStatus::Confirmed).await
+}
+
+#[rocket::put("/applications/<user_id>/reject")]
+pub async fn reject_application(
+    mut db: Connection<DbConn>,
+    _admin: AdminUser,
+    user_id: i32,
+) -> Result<Value, ApiError> {
+    review(&mut db, user_id, RequestStatus::Rejected).await
+}
@@ -0,0 +1,308 @@
+use crate::errors::ApiError;
+use crate::models::email_verification_token::EmailVerification;
+use crate::models::password_reset_token::{PasswordResetConfirmation, PasswordResetRequest};
+use crate::models::session::RefreshRequest;
+use crate::models::user::{normalize_email, Credentials, RegisterUser, User, UserRole};
+use crate::repositories::one_time_token::{
+    EmailVerificationTokenRepository, PasswordResetTokenRepository,
+};
+use crate::repositories::session::SessionRepository;
+use crate::repositories::user::UserRepository;
+use crate::utils::auth_token::{TokenSigner, ACCESS_TOKEN_TTL_SECONDS};
+use crate::utils::mailer::{Email, Mailer};
+use crate::utils::rate_limiter::{LoginRateLimiter, ACCOUNT_LOCKOUT};
+use crate::utils::secure_token::SecureToken;
+use crate::utils::validation::Validate;
+
+use std::time::Duration;
+
+use diesel::result::{DatabaseErrorKind, Error};
+use diesel_async::AsyncPgConnection;
+use rocket::http::Status;
+use rocket::response::status::{Accepted, Custom, NoContent};
+use rocket::serde::json::{json, Json, Value};
+use rocket::State;
+use rocket_db_pools::Connection;
+
+use super::{AuthenticatedUser, ClientInfo, DbConn};
+
+async fn send_verification_email(
+    c: &mut AsyncPgConnection,
+    mailer: &dyn Mailer,
+    user: &User,
+) -> Result<(), String> {
+    let (token, token_hash) = SecureToken::generate();
+    EmailVerificationTokenRepository::create(c, user.id, token_hash)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let email = Email {
+        to: user.email.clone(),
+        subject: "Verify your email address".to_string(),
+        body: format!(
+            "Use the following code to verify your email address: {}\n\n\
+             The code expires in 48 hours.",
+            token
+        ),
+    };
+    mailer.send(email).await
+}
+
+#[rocket::post("/auth/register", format = "json", data = "<registration>")]
+pub async fn register(
+    mut db: Connection<DbConn>,
+    mailer: &State<Box<dyn Mailer>>,
+    registration: Json<RegisterUser>,
+) -> Result<Custom<Value>, ApiError> {
+    registration.validate()?;
+
+    let user = UserRepository::create(&mut db, registration.into_inner(), UserRole::User)
+        .await
+        .map_err(|e| match e {
+            Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                ApiError::Conflict("Email is already registered".to_string())
+            }
+            _ => e.into(),
+        })?;
+
+    // The account exists at this point; a failed email can be retried with the resend endpoint.
+    if let Err(e) = send_verification_email(&mut db, mailer.as_ref(), &user).await {
+        rocket::error!("Cannot send verification email to {}: {}", user.email, e);
+    }
+
+    Ok(Custom(Status::Created, json!(user)))
+}
+
+#[rocket::post("/auth/verify-email", format = "json", data = "<verification>")]
+pub async fn verify_email(
+    mut db: Connection<DbConn>,
+    verification: Json<EmailVerification>,
+) -> Result<Value, ApiError> {
+    let token_hash = SecureToken::hash(&verification.token);
+
+    db.build_transaction()
+        .run(|conn| {
+            Box::pin(async move {
+                let token = EmailVerificationTokenRepository::consume(conn, &token_hash).await?;
+                UserRepository::mark_email_verified(conn, token.user_id).await
+            })
+        })
+        .await
+        .map(|user| json!(user))
+        .map_err(|e| match e {
+            Error::NotFound => {
+                ApiError::Validation("Invalid or expired verification token".to_string())
+            }
+            _ => e.into(),
+        })
+}
+
+#[rocket::post("/auth/verify-email/resend")]
+pub async fn resend_verification_email(
+    mut db: Connection<DbConn>,
+    mailer: &State<Box<dyn Mailer>>,
+    user: AuthenticatedUser,
+) -> Result<Accepted<()>, ApiError> {
+    if user.0.email_verified_at.is_some() {
+        return Err(ApiError::Conflict("Email is already verified".to_string()));
+    }
+
+    send_verification_email(&mut db, mailer.as_ref(), &user.0)
+        .await
+        .map(|_| Accepted(()))
+        .map_err(ApiError::internal)
+}
+
+fn throttled(retry_after: Duration) -> ApiError {
+    // Round up so clients never retry a moment too early.
+    ApiError::TooManyRequests {
+        retry_after: retry_after.as_secs() + 1,
+    }
+}
+
+#[rocket::post("/auth/login", format = "json", data = "<credentials>")]
+pub async fn login(
+    mut db: Connection<DbConn>,
+    signer: &State<TokenSigner>,
+    limiter: &State<LoginRateLimiter>,
+    client: ClientInfo,
+    credentials: Json<Credentials>,
+) -> Result<Value, ApiError> {
+    let ip = client.ip_address.clone();
+    let credentials = Credentials {
+        email: normalize_email(&credentials.email),
+        ..credentials.into_inner()
+    };
+
+    if let Some(ip) = &ip {
+        if let Some(retry_after) = limiter.ip_retry_after(ip).await {
+            return Err(throttled(retry_after));
+        }
+    }
+
+    if let Some(retry_after) = limiter.account_retry_after(&credentials.email).await {
+        return Err(throttled(retry_after));
+    }
+
+    let user = match UserRepository::authenticate(&mut db, &credentials).await? {
+        Some(user) => user,
+        None => {
+            if limiter
+                .record_failure(ip.as_deref(), &credentials.email)
+                .await
+            {
+                lock_account(&mut db, &credentials.email).await?;
+            }
+            return Err(ApiError::Unauthorized("Wrong credentials".to_string()));
+        }
+    };
+
+    limiter.record_success(&credentials.email).await;
+
+    if user.disabled_at.is_some() {
+        return Err(ApiError::Forbidden("Account is disabled".to_string()));
+    }
+
+    let (refresh_token, refresh_token_hash) = SecureToken::generate();
+    let session = SessionRepository::create(
+        &mut db,
+        user.id,
+        refresh_token_hash,
+        client.user_agent,
+        client.ip_address,
+    )
+    .await?;
+
+    Ok(json!({
+        "token": signer.issue(user.id, session.id),
+        "refresh_token": refresh_token,
+        "expires_in": ACCESS_TOKEN_TTL_SECONDS,
+        "user": user,
+    }))
+}
+
+#[rocket::post("/auth/refresh", format = "json", data = "<request>")]
+pub async fn refresh(
+    mut db: Connection<DbConn>,
+    signer: &State<TokenSigner>,
+    request: Json<RefreshRequest>,
+) -> Result<Value, ApiError> {
+    let (refresh_token, refresh_token_hash) = SecureToken::generate();
+
+    let session = SessionRepository::rotate(
+        &mut db,
+        &SecureToken::hash(&request.refresh_token),
+        refresh_token_hash,
+    )
+    .await
+    .map_err(|e| match e {
+        Error::NotFound => ApiError::Unauthorized("Invalid or expired refresh token".to_string()),
+        _ => e.into(),
+    })?;
+
+    let user = UserRepository::find(&mut db, session.user_id).await?;
+    if user.disabled_at.is_some() {
+        return Err(ApiError::Forbidden("Account is disabled".to_string()));
+    }
+
+    Ok(json!({
+        "token": signer.issue(user.id, session.id),
+        "refresh_token": refresh_token,
+        "expires_in": ACCESS_TOKEN_TTL_SECONDS,
+    }))
+}
+
+#[rocket::post("/auth/logout")]
+pub async fn logout(
+    mut db: Connection<DbConn>,
+    user: AuthenticatedUser,
+) -> Result<NoContent, ApiError> {
+    let AuthenticatedUser(user, session_id) = user;
+
+    SessionRepository::revoke(&mut db, session_id, user.id)
+        .await
+        .map(|_| NoContent)
+        .map_err(ApiError::from)
+}
+
+/// Records the lock the limiter imposed on the account, for admins to see and lift.
+async fn lock_account(c: &mut AsyncPgConnection, email: &str) -> Result<(), ApiError> {
+    let user = match UserRepository::find_by_email(c, email).await {
+        Ok(user) => user,
+        Err(Error::NotFound) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let locked_until = chrono::Utc::now() + ACCOUNT_LOCKOUT;
+    UserRepository::set_locked_until(c, user.id, Some(locked_until.naive_utc()))
+        .await
+        .map(|_| ())
+        .map_err(ApiError::from)
+}
+
+#[rocket::post("/auth/password-reset", format = "json", data = "<request>")]
+pub async fn request_password_reset(
+    mut db: Connection<DbConn>,
+    mailer: &State<Box<dyn Mailer>>,
+    request: Json<PasswordResetRequest>,
+) -> Result<Accepted<()>, ApiError> {
+    // Unknown emails get the same answer so the endpoint cannot be used to probe accounts.
+    let user = match UserRepository::find_by_email(&mut db, &request.email).await {
+        Ok(user) => user,
+        Err(Error::NotFound) => return Ok(Accepted(())),
+        Err(e) => return Err(e.into()),
+    };
+
+    let (token, token_hash) = SecureToken::generate();
+    PasswordResetTokenRepository::create(&mut db, user.id, token_hash).await?;
+
+    let email = Email {
+        to: user.email.clone(),
+        subject: "Password reset".to_string(),
+        body: format!(
+            "Use the following code to set a new password: {}\n\n\
+             The code expires in one hour. If you did not ask for a reset, ignore this email.",
+            token
+        ),
+    };
+    // A failure must not change the answer either, it would tell that the account exists
+    if let Err(e) = mailer.send(email).await {
+        rocket::error!("Cannot send password reset email to {}: {}", user.email, e);
+    }
+
+    Ok(Accepted(()))
+}
+
+#[rocket::post(
+    "/auth/password-reset/confirm",
+    format = "json",
+    data = "<confirmation>"
+)]
+pub async fn confirm_password_reset(
+    mut db: Connection<DbConn>,
+    confirmation: Json<PasswordResetConfirmation>,
+) -> Result<NoContent, ApiError> {
+    confirmation.validate()?;
+
+    let confirmation = confirmation.into_inner();
+    let token_hash = SecureToken::hash(&confirmation.token);
+
+    db.build_transaction()
+        .run(|conn| {
+            Box::pin(async move {
+                let token = PasswordResetTokenRepository::consume(conn, &token_hash).await?;
+                UserRepository::update_password(conn, token.user_id, &confirmation.password)
+                    .await?;
+                SessionRepository::revoke_all_for_user(conn, token.user_id).await?;
+                Ok::<_, Error>(())
+            })
+        })
+        .await
+        .map(|_| NoContent)
+        .map_err(|e| match e {
+            Error::NotFound => {
+                ApiError::Validation("Invalid or expired password reset token".to_string())
+            }
+            _ => e.into(),
+        })
+}
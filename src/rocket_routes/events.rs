@@ -1,66 +1,91 @@
-use crate::errors::server_error;
+use crate::errors::ApiError;
 use crate::models::event::{Event, NewEvent};
+use crate::models::user::UserType;
 use crate::repositories::event::EventRepository;
+use crate::repositories::user::UserRepository;
+use crate::utils::validation::Validate;
 
+use diesel::result::{DatabaseErrorKind, Error};
 use rocket::http::Status;
 use rocket::response::status::{Custom, NoContent};
 use rocket::serde::json::{json, Json, Value};
 use rocket_db_pools::Connection;
 
-use super::DbConn;
+use super::{AdminUser, DbConn};
 
 #[rocket::get("/events/<id>")]
-pub async fn get_event<'a>(
-    mut db: Connection<DbConn>,
-    id: i32,
-) -> Result<Custom<Value>, Custom<Value>> {
+pub async fn get_event(mut db: Connection<DbConn>, id: i32) -> Result<Custom<Value>, ApiError> {
     EventRepository::find(&mut db, id)
         .await
         .map(|event| Custom(Status::Ok, json!(event)))
-        .map_err(|e| server_error(e.into()))
+        .map_err(ApiError::from)
 }
 
 #[rocket::get("/events/<id>/with_program")]
-pub async fn get_event_with_program<'a>(
+pub async fn get_event_with_program(
     mut db: Connection<DbConn>,
     id: i32,
-) -> Result<Custom<Value>, Custom<Value>> {
+) -> Result<Custom<Value>, ApiError> {
     EventRepository::find_event_with_program(&mut db, id)
         .await
         .map(|event| Custom(Status::Ok, json!(event)))
-        .map_err(|e| server_error(e.into()))
+        .map_err(ApiError::from)
+}
+
+#[rocket::get("/events/<id>/participants?<user_type>")]
+pub async fn get_event_participants(
+    mut db: Connection<DbConn>,
+    id: i32,
+    user_type: Option<UserType>,
+) -> Result<Custom<Value>, ApiError> {
+    UserRepository::find_participants(&mut db, id, user_type)
+        .await
+        .map(|participants| Custom(Status::Ok, json!(participants)))
+        .map_err(ApiError::from)
 }
 
 #[rocket::post("/events", format = "json", data = "<event>")]
 pub async fn create_event(
     mut db: Connection<DbConn>,
+    _admin: AdminUser,
     event: Json<NewEvent>,
-) -> Result<Custom<Value>, Custom<Value>> {
+) -> Result<Custom<Value>, ApiError> {
+    event.validate()?;
+
     EventRepository::create(&mut db, event.into_inner())
         .await
         .map(|event| Custom(Status::Created, json!(event)))
-        .map_err(|e| server_error(e.into()))
+        .map_err(ApiError::from)
 }
 
 #[rocket::put("/events/<id>", format = "json", data = "<event>")]
-pub async fn update_event<'a>(
+pub async fn update_event(
     mut db: Connection<DbConn>,
+    _admin: AdminUser,
     id: i32,
     event: Json<Event>,
-) -> Result<Custom<Value>, Custom<Value>> {
+) -> Result<Custom<Value>, ApiError> {
+    event.validate()?;
+
     EventRepository::update(&mut db, id, event.into_inner())
         .await
         .map(|event| Custom(Status::Ok, json!(event)))
-        .map_err(|e| server_error(e.into()))
+        .map_err(ApiError::from)
 }
 
 #[rocket::delete("/events/<id>")]
-pub async fn delete_event<'a>(
+pub async fn delete_event(
     mut db: Connection<DbConn>,
+    _admin: AdminUser,
     id: i32,
-) -> Result<NoContent, Custom<Value>> {
+) -> Result<NoContent, ApiError> {
     EventRepository::delete(&mut db, id)
         .await
         .map(|_| NoContent)
-        .map_err(|e| server_error(e.into()))
+        .map_err(|e| match e {
+            Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                ApiError::Conflict("Event still has programs or participants".to_string())
+            }
+            _ => e.into(),
+        })
 }
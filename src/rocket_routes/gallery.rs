@@ -1,108 +1,281 @@
-use super::DbConn;
-use crate::errors::server_error;
-use crate::models::gallery::{NewGallery, UpdateGallery};
+use std::sync::Arc;
+
+use super::{AdminUser, DbConn};
+use crate::errors::ApiError;
+use crate::models::gallery::{
+    Gallery, GalleryImageIds, GalleryImagesUpload, NewGallery, UpdateGallery,
+};
 use crate::repositories::gallery::GalleryRepository;
-use crate::repositories::image::ImageRepository;
+use crate::repositories::image::ImageChanges;
 use crate::utils::form_data::{FormData, FromFormData};
-use crate::utils::form_fields::FormConfig;
+use crate::utils::form_fields::{FormConfig, ImageFormData};
+use crate::utils::storage::StorageBackend;
+use crate::utils::validation::Validator;
+use diesel_async::AsyncPgConnection;
 use rocket::http::ContentType;
-use rocket::{
-    response::status::Custom,
-    serde::json::{json, Value},
-};
+use rocket::response::status::NoContent;
+use rocket::serde::json::serde_json::Map;
+use rocket::serde::json::{json, Json, Value};
+use rocket::State;
 use rocket_db_pools::Connection;
 
 #[rocket::get("/gallery/<id>")]
-pub async fn get_gallery<'a>(mut db: Connection<DbConn>, id: i32) -> Result<Value, Custom<Value>> {
-    GalleryRepository::find(&mut db, id)
+pub async fn get_gallery(mut db: Connection<DbConn>, id: i32) -> Result<Value, ApiError> {
+    GalleryRepository::find_with_images(&mut db, id)
         .await
         .map(|gallery| json!(gallery))
-        .map_err(|e| server_error(e.into()))
+        .map_err(ApiError::from)
 }
 
 #[rocket::get("/gallery")]
-pub async fn get_all_galleries<'a>(mut db: Connection<DbConn>) -> Result<Value, Custom<Value>> {
+pub async fn get_all_galleries(mut db: Connection<DbConn>) -> Result<Value, ApiError> {
     GalleryRepository::all(&mut db)
         .await
         .map(|galleries| json!(galleries))
-        .map_err(|e| server_error(e.into()))
+        .map_err(ApiError::from)
 }
 
 #[rocket::post("/gallery", format = "multipart/form-data", data = "<data>")]
 pub async fn create_gallery<'a>(
     mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
     content_type: &'a ContentType,
     data: rocket::Data<'a>,
-) -> Result<Value, Custom<Value>> {
+) -> Result<Value, ApiError> {
     let config = FormConfig::<NewGallery>::new();
     let form_data = FormData::from_multipart(content_type, data, &config).await?;
 
-    let repo = ImageRepository::new()
-        .await
-        .map_err(|e| server_error(e.into()))?;
+    let image_field = form_data.image_field.clone();
+    let mut new_gallery = NewGallery::from_form_data(form_data)?;
+
+    let mut changes = ImageChanges::new(storage);
+
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                if let Some(image_data) = image_field {
+                    new_gallery.featured_image_id = changes.save_image(conn, image_data).await?.id;
+                }
+
+                Ok::<_, ApiError>(GalleryRepository::create(conn, new_gallery).await?)
+            })
+        })
+        .await;
 
-    let image_id = if let Some(image_data) = form_data.image_field.clone() {
-        let image = repo
-            .save_image(&mut db, image_data)
-            .await
-            .map_err(|e| server_error(e.into()))?;
-        Some(image.id)
-    } else {
-        None
-    };
+    changes.finish(result).await.map(|gallery| json!(gallery))
+}
 
-    let mut new_gallery = NewGallery::from_form_data(form_data).unwrap();
-    new_gallery.featured_image_id = image_id.unwrap_or_default();
+#[rocket::post("/gallery", format = "json", data = "<body>")]
+pub async fn create_gallery_json(
+    mut db: Connection<DbConn>,
+    _admin: AdminUser,
+    body: Json<Map<String, Value>>,
+) -> Result<Value, ApiError> {
+    let form_data = FormData::from_json::<NewGallery>(body.into_inner())?;
+    let new_gallery = NewGallery::from_form_data(form_data)?;
 
     GalleryRepository::create(&mut db, new_gallery)
         .await
         .map(|gallery| json!(gallery))
-        .map_err(|e| server_error(e.into()))
+        .map_err(ApiError::from)
 }
 
 #[rocket::put("/gallery/<id>", format = "multipart/form-data", data = "<data>")]
 pub async fn update_gallery<'a>(
     mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
     id: i32,
     content_type: &'a ContentType,
     data: rocket::Data<'a>,
-) -> Result<Value, Custom<Value>> {
+) -> Result<Value, ApiError> {
     let config = FormConfig::<UpdateGallery>::new();
     let form_data = FormData::from_multipart(content_type, data, &config).await?;
 
-    let repo = ImageRepository::new()
-        .await
-        .map_err(|e| server_error(e.into()))?;
-
-    let image_id = if let Some(image_data) = form_data.image_field.clone() {
-        let image = repo
-            .save_image(&mut db, image_data)
-            .await
-            .map_err(|e| server_error(e.into()))?;
-        Some(image.id)
-    } else {
-        None
-    };
+    let image_field = form_data.image_field.clone();
+    let update_gallery = UpdateGallery::from_form_data(form_data)?;
 
-    let mut update_gallery = UpdateGallery::from_form_data(form_data).unwrap();
+    update(&mut db, storage, id, update_gallery, image_field)
+        .await
+        .map(|gallery| json!(gallery))
+}
 
-    if let Some(image_id) = image_id {
-        update_gallery.featured_image_id = Some(image_id);
-    }
+#[rocket::put("/gallery/<id>", format = "json", data = "<body>")]
+pub async fn update_gallery_json(
+    mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
+    id: i32,
+    body: Json<Map<String, Value>>,
+) -> Result<Value, ApiError> {
+    let form_data = FormData::from_json::<UpdateGallery>(body.into_inner())?;
+    let update_gallery = UpdateGallery::from_form_data(form_data)?;
 
-    GalleryRepository::update(&mut db, id, update_gallery)
+    update(&mut db, storage, id, update_gallery, None)
         .await
         .map(|gallery| json!(gallery))
-        .map_err(|e| server_error(e.into()))
 }
 
+/// Updates the gallery with `image_field` saved as its new featured image, if given,
+/// and releases the one it had before when that changed.
+async fn update(
+    db: &mut AsyncPgConnection,
+    storage: &Arc<dyn StorageBackend>,
+    id: i32,
+    mut update_gallery: UpdateGallery,
+    image_field: Option<ImageFormData>,
+) -> Result<Gallery, ApiError> {
+    let mut changes = ImageChanges::new(storage);
+
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                let old_gallery = GalleryRepository::find(conn, id).await?;
+
+                if let Some(image_data) = image_field {
+                    update_gallery.featured_image_id =
+                        Some(changes.save_image(conn, image_data).await?.id);
+                }
+
+                let updated_gallery = GalleryRepository::update(conn, id, update_gallery).await?;
+
+                if updated_gallery.featured_image_id != old_gallery.featured_image_id {
+                    changes
+                        .release_image(conn, old_gallery.featured_image_id)
+                        .await?;
+                }
+
+                Ok::<_, ApiError>(updated_gallery)
+            })
+        })
+        .await;
+
+    changes.finish(result).await
+}
+
+/// Deletes the gallery with its featured image and the images added to it, except those
+/// still used anywhere else.
 #[rocket::delete("/gallery/<id>")]
-pub async fn delete_gallery<'a>(
+pub async fn delete_gallery(
     mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
     id: i32,
-) -> Result<rocket::response::status::NoContent, Custom<Value>> {
-    GalleryRepository::delete(&mut db, id)
-        .await
-        .map(|_| rocket::response::status::NoContent)
-        .map_err(|e| server_error(e.into()))
+) -> Result<NoContent, ApiError> {
+    let mut changes = ImageChanges::new(storage);
+
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                let gallery = GalleryRepository::find(conn, id).await?;
+                let image_ids = GalleryRepository::find_image_ids(conn, id).await?;
+
+                GalleryRepository::delete(conn, id).await?;
+
+                changes
+                    .release_image(conn, gallery.featured_image_id)
+                    .await?;
+                for image_id in image_ids {
+                    changes.release_image(conn, image_id).await?;
+                }
+
+                Ok::<_, ApiError>(())
+            })
+        })
+        .await;
+
+    changes.finish(result).await.map(|_| NoContent)
+}
+
+#[rocket::post(
+    "/gallery/<id>/images",
+    format = "multipart/form-data",
+    data = "<data>"
+)]
+pub async fn add_gallery_images<'a>(
+    mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
+    id: i32,
+    content_type: &'a ContentType,
+    data: rocket::Data<'a>,
+) -> Result<Value, ApiError> {
+    let config = FormConfig::<GalleryImagesUpload>::new();
+    let form_data = FormData::from_multipart(content_type, data, &config).await?;
+
+    GalleryRepository::find(&mut db, id).await?;
+
+    let mut changes = ImageChanges::new(storage);
+
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                let image_ids = changes
+                    .save_multiple_images(conn, form_data.image_fields)
+                    .await?
+                    .into_iter()
+                    .map(|image| image.id)
+                    .collect();
+
+                GalleryRepository::add_images(conn, id, image_ids).await?;
+
+                Ok::<_, ApiError>(GalleryRepository::find_with_images(conn, id).await?)
+            })
+        })
+        .await;
+
+    changes.finish(result).await.map(|gallery| json!(gallery))
+}
+
+/// Removes the images from the gallery and deletes those no longer used anywhere else.
+/// Nothing is removed if any of the ids is not in the gallery.
+#[rocket::delete("/gallery/<id>/images", format = "json", data = "<body>")]
+pub async fn remove_gallery_images(
+    mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
+    id: i32,
+    body: Json<GalleryImageIds>,
+) -> Result<NoContent, ApiError> {
+    let image_ids = body.into_inner().image_ids;
+
+    GalleryRepository::find(&mut db, id).await?;
+
+    let mut changes = ImageChanges::new(storage);
+
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                let removed = GalleryRepository::remove_images(conn, id, image_ids.clone()).await?;
+
+                let mut validator = Validator::new();
+                for image_id in image_ids.iter().filter(|id| !removed.contains(id)) {
+                    validator.error(
+                        "image_ids",
+                        format!("image {} is not in this gallery", image_id),
+                    );
+                }
+                validator.finish()?;
+
+                for image_id in removed {
+                    changes.release_image(conn, image_id).await?;
+                }
+
+                Ok::<_, ApiError>(())
+            })
+        })
+        .await;
+
+    changes.finish(result).await.map(|_| NoContent)
 }
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::errors::ApiError;
+use crate::models::image::{CompleteUpload, ImageFilters, ImageUpload, PresignUpload};
+use crate::repositories::image::{ImageChanges, ImageRepository};
+use crate::utils::form_data::FormData;
+use crate::utils::form_fields::FormConfig;
+use crate::utils::storage::{is_upload_key, new_upload_key, StorageBackend};
+use crate::utils::validation::Validate;
+
+use rocket::http::{ContentType, Status};
+use rocket::response::status::{Custom, NoContent};
+use rocket::serde::json::{json, Json, Value};
+use rocket::Data;
+use rocket::State;
+use rocket_db_pools::Connection;
+
+use super::{AdminUser, DbConn};
+
+#[rocket::get("/images?<filters..>")]
+pub async fn get_images(
+    mut db: Connection<DbConn>,
+    filters: ImageFilters,
+) -> Result<Value, ApiError> {
+    ImageRepository::find_page(&mut db, &filters)
+        .await
+        .map(|(images, total)| {
+            json!({
+                "images": images,
+                "page": filters.page,
+                "per_page": filters.per_page,
+                "total": total,
+            })
+        })
+        .map_err(ApiError::from)
+}
+
+#[rocket::get("/images/<id>")]
+pub async fn get_image(mut db: Connection<DbConn>, id: i32) -> Result<Value, ApiError> {
+    ImageRepository::find_with_variants(&mut db, id)
+        .await
+        .map(|image| json!(image))
+        .map_err(ApiError::from)
+}
+
+#[rocket::post("/images", format = "multipart/form-data", data = "<data>")]
+pub async fn upload_image<'a>(
+    mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
+    content_type: &'a ContentType,
+    data: Data<'a>,
+) -> Result<Custom<Value>, ApiError> {
+    let config = FormConfig::<ImageUpload>::new();
+    let form_data = FormData::from_multipart(content_type, data, &config).await?;
+
+    let repo = ImageRepository::new(storage);
+
+    let image_data = form_data
+        .image_field
+        .ok_or_else(|| ApiError::Validation("Missing required field: image".to_string()))?;
+    let image = repo.save_library_image(&mut db, image_data).await?;
+    let image = ImageRepository::find_with_variants(&mut db, image.id).await?;
+
+    Ok(Custom(Status::Created, json!(image)))
+}
+
+/// How long a presigned upload URL stays valid.
+const PRESIGN_EXPIRES_IN: Duration = Duration::from_secs(15 * 60);
+
+/// Hands out a URL to `PUT` one file straight to the storage, so large photos do not
+/// pass through the server. Register the file afterwards with `POST /images/complete`.
+#[rocket::post("/images/presign", format = "json", data = "<upload>")]
+pub async fn presign_upload(
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
+    upload: Json<PresignUpload>,
+) -> Result<Value, ApiError> {
+    upload.validate()?;
+
+    let key = new_upload_key(&upload.file_name);
+    let presigned = storage
+        .presign_upload(&key, &upload.content_type, PRESIGN_EXPIRES_IN)
+        .await
+        .map_err(ApiError::storage)?
+        .ok_or_else(|| {
+            ApiError::NotImplemented(
+                "Direct uploads are not supported by the configured storage".to_string(),
+            )
+        })?;
+
+    Ok(json!({
+        "key": key,
+        "method": "PUT",
+        "url": presigned.url,
+        "headers": presigned.headers,
+        "expires_in": PRESIGN_EXPIRES_IN.as_secs(),
+    }))
+}
+
+#[rocket::post("/images/complete", format = "json", data = "<upload>")]
+pub async fn complete_upload(
+    mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
+    upload: Json<CompleteUpload>,
+) -> Result<Custom<Value>, ApiError> {
+    if !is_upload_key(&upload.key) {
+        return Err(ApiError::Validation(
+            "Not a key handed out by /images/presign".to_string(),
+        ));
+    }
+    if ImageRepository::find_by_key(&mut db, &upload.key)
+        .await?
+        .is_some()
+    {
+        return Err(ApiError::Conflict(
+            "The upload is already registered".to_string(),
+        ));
+    }
+    let size = storage
+        .size(&upload.key)
+        .await
+        .map_err(ApiError::storage)?
+        .ok_or_else(|| ApiError::NotFound("Upload not found".to_string()))?;
+
+    let repo = ImageRepository::new(storage);
+    let image = repo.save_uploaded(&mut db, &upload.key, size).await?;
+    let image = ImageRepository::find_with_variants(&mut db, image.id).await?;
+
+    Ok(Custom(Status::Created, json!(image)))
+}
+
+#[rocket::delete("/images/<id>")]
+pub async fn delete_image(
+    mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
+    id: i32,
+) -> Result<NoContent, ApiError> {
+    let mut changes = ImageChanges::new(storage);
+
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                ImageRepository::find(conn, id).await?;
+
+                if ImageRepository::is_referenced(conn, id).await? {
+                    return Err(ApiError::Conflict(
+                        "Image is still used by news, programs, galleries or users".to_string(),
+                    ));
+                }
+
+                changes.delete_image(conn, id).await?;
+
+                Ok(())
+            })
+        })
+        .await;
+
+    changes.finish(result).await.map(|_| NoContent)
+}
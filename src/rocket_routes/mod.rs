@@ -1,11 +1,23 @@
 use rocket::fairing::Fairing;
-
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
 use rocket::{Request, Response};
+use rocket_db_pools::Connection;
+
+use crate::models::user::{User, UserRole};
+use crate::repositories::session::SessionRepository;
+use crate::repositories::user::UserRepository;
+use crate::utils::auth_token::TokenSigner;
 
+pub mod admin;
+pub mod applications;
+pub mod auth;
 pub mod events;
 pub mod gallery;
+pub mod images;
 pub mod news;
 pub mod programs;
+pub mod users;
 
 #[derive(rocket_db_pools::Database)]
 #[database("postgres")]
@@ -29,3 +41,83 @@ impl Fairing for Cors {
         res.set_raw_header("Access-Control-Allow-Credentials", "true");
     }
 }
+
+/// A user identified by a valid `Authorization: Bearer <token>` header, together with
+/// the id of the session the token was issued for. Tokens of revoked sessions get 401,
+/// disabled accounts 403.
+pub struct AuthenticatedUser(pub User, pub i32);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuthenticatedUser {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "));
+
+        let claims = match (token, req.rocket().state::<TokenSigner>()) {
+            (Some(token), Some(signer)) => signer.verify(token.trim()),
+            _ => None,
+        };
+
+        if let Some(claims) = claims {
+            let mut db = match req.guard::<Connection<DbConn>>().await {
+                Outcome::Success(db) => db,
+                _ => return Outcome::Error((Status::InternalServerError, ())),
+            };
+
+            let session =
+                SessionRepository::find_active(&mut db, claims.session_id, claims.user_id).await;
+
+            if let (Ok(session), Ok(user)) =
+                (session, UserRepository::find(&mut db, claims.user_id).await)
+            {
+                if user.disabled_at.is_some() {
+                    return Outcome::Error((Status::Forbidden, ()));
+                }
+                return Outcome::Success(AuthenticatedUser(user, session.id));
+            }
+        }
+
+        Outcome::Error((Status::Unauthorized, ()))
+    }
+}
+
+/// Client details recorded with new sessions.
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientInfo {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(ClientInfo {
+            ip_address: req.client_ip().map(|ip| ip.to_string()),
+            user_agent: req.headers().get_one("User-Agent").map(String::from),
+        })
+    }
+}
+
+/// An authenticated user with the `Admin` role. Other users get 403.
+pub struct AdminUser(pub User);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminUser {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.guard::<AuthenticatedUser>().await {
+            Outcome::Success(AuthenticatedUser(user, _)) if user.role == UserRole::Admin => {
+                Outcome::Success(AdminUser(user))
+            }
+            Outcome::Success(_) => Outcome::Error((Status::Forbidden, ())),
+            Outcome::Error(e) => Outcome::Error(e),
+            Outcome::Forward(status) => Outcome::Forward(status),
+        }
+    }
+}
@@ -1,139 +1,182 @@
-use super::DbConn;
-use crate::errors::server_error;
-use crate::models::news::{NewNews, UpdateNews};
-use crate::repositories::image::ImageRepository;
+use std::sync::Arc;
+
+use super::{AdminUser, DbConn};
+use crate::errors::ApiError;
+use crate::models::news::{NewNews, News, UpdateNews};
+use crate::repositories::image::ImageChanges;
 use crate::repositories::news::NewsRepository;
 use crate::utils::form_data::{FormData, FromFormData};
-use crate::utils::form_fields::FormConfig;
-use diesel::result::Error;
+use crate::utils::form_fields::{FormConfig, ImageFormData};
+use crate::utils::storage::StorageBackend;
+use diesel_async::AsyncPgConnection;
 use rocket::http::ContentType;
-use rocket::{
-    response::status::Custom,
-    serde::json::{json, Value},
-};
+use rocket::serde::json::serde_json::Map;
+use rocket::serde::json::{json, Json, Value};
+use rocket::State;
 use rocket_db_pools::Connection;
 
 #[rocket::get("/news/<id>")]
-pub async fn get_news<'a>(mut db: Connection<DbConn>, id: i32) -> Result<Value, Custom<Value>> {
+pub async fn get_news(mut db: Connection<DbConn>, id: i32) -> Result<Value, ApiError> {
     NewsRepository::find(&mut db, id)
         .await
         .map(|news| json!(news))
-        .map_err(|e| server_error(e.into()))
+        .map_err(ApiError::from)
 }
 
 #[rocket::get("/news")]
-pub async fn get_all_news<'a>(mut db: Connection<DbConn>) -> Result<Value, Custom<Value>> {
+pub async fn get_all_news(mut db: Connection<DbConn>) -> Result<Value, ApiError> {
     NewsRepository::all(&mut db)
         .await
         .map(|news| json!(news))
-        .map_err(|e| server_error(e.into()))
+        .map_err(ApiError::from)
 }
 
 #[rocket::post("/news", format = "multipart/form-data", data = "<data>")]
 pub async fn create_news<'a>(
     mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
     content_type: &'a ContentType,
     data: rocket::Data<'a>,
-) -> Result<Value, Custom<Value>> {
+) -> Result<Value, ApiError> {
     let config = FormConfig::<NewNews>::new();
     let form_data = FormData::from_multipart(content_type, data, &config).await?;
 
-    let repo = ImageRepository::new()
-        .await
-        .map_err(|e| server_error(e.into()))?;
+    let image_field = form_data.image_field.clone();
+    let mut new_news = NewNews::from_form_data(form_data)?;
 
-    let image_id = if let Some(image_data) = form_data.image_field.clone() {
-        let image = repo
-            .save_image(&mut db, image_data)
-            .await
-            .map_err(|e| server_error(e.into()))?;
-        Some(image.id)
-    } else {
-        None
-    };
+    let mut changes = ImageChanges::new(storage);
 
-    let mut new_news = NewNews::from_form_data(form_data).unwrap();
-    new_news.image_id = image_id.unwrap_or_default();
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                if let Some(image_data) = image_field {
+                    new_news.image_id = changes.save_image(conn, image_data).await?.id;
+                }
+
+                Ok::<_, ApiError>(NewsRepository::create(conn, new_news).await?)
+            })
+        })
+        .await;
+
+    changes.finish(result).await.map(|news| json!(news))
+}
+
+#[rocket::post("/news", format = "json", data = "<body>")]
+pub async fn create_news_json(
+    mut db: Connection<DbConn>,
+    _admin: AdminUser,
+    body: Json<Map<String, Value>>,
+) -> Result<Value, ApiError> {
+    let form_data = FormData::from_json::<NewNews>(body.into_inner())?;
+    let new_news = NewNews::from_form_data(form_data)?;
 
     NewsRepository::create(&mut db, new_news)
         .await
         .map(|news| json!(news))
-        .map_err(|e| server_error(e.into()))
+        .map_err(ApiError::from)
 }
 
 #[rocket::put("/news/<id>", format = "multipart/form-data", data = "<data>")]
 pub async fn update_news<'a>(
     mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
     id: i32,
     content_type: &'a ContentType,
     data: rocket::Data<'a>,
-) -> Result<Value, Custom<Value>> {
+) -> Result<Value, ApiError> {
     let config = FormConfig::<UpdateNews>::new();
     let form_data = FormData::from_multipart(content_type, data, &config).await?;
 
-    let repo = ImageRepository::new()
+    let image_field = form_data.image_field.clone();
+    let update_news = UpdateNews::from_form_data(form_data)?;
+
+    update(&mut db, storage, id, update_news, image_field)
+        .await
+        .map(|news| json!(news))
+}
+
+#[rocket::put("/news/<id>", format = "json", data = "<body>")]
+pub async fn update_news_json(
+    mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
+    id: i32,
+    body: Json<Map<String, Value>>,
+) -> Result<Value, ApiError> {
+    let form_data = FormData::from_json::<UpdateNews>(body.into_inner())?;
+    let update_news = UpdateNews::from_form_data(form_data)?;
+
+    update(&mut db, storage, id, update_news, None)
         .await
-        .map_err(|e| server_error(e.into()))?;
-
-    let image_id = if let Some(image_data) = form_data.image_field.clone() {
-        let image = repo
-            .save_image(&mut db, image_data)
-            .await
-            .map_err(|e| server_error(e.into()))?;
-        Some(image.id)
-    } else {
-        None
-    };
-
-    let mut update_news = UpdateNews::from_form_data(form_data).unwrap();
-    if let Some(image_id) = image_id {
-        update_news.image_id = Some(image_id);
-    }
+        .map(|news| json!(news))
+}
+
+/// Updates the news with `image_field` saved as its new image, if given, and releases
+/// the image it used before when that changed.
+async fn update(
+    db: &mut AsyncPgConnection,
+    storage: &Arc<dyn StorageBackend>,
+    id: i32,
+    mut update_news: UpdateNews,
+    image_field: Option<ImageFormData>,
+) -> Result<News, ApiError> {
+    let mut changes = ImageChanges::new(storage);
 
     let result = db
         .build_transaction()
         .run(|conn| {
+            let changes = &mut changes;
             Box::pin(async move {
                 let old_news = NewsRepository::find(conn, id).await?;
 
+                if let Some(image_data) = image_field {
+                    update_news.image_id = Some(changes.save_image(conn, image_data).await?.id);
+                }
+
                 let updated_news = NewsRepository::update(conn, id, update_news).await?;
 
-                if image_id.is_some() {
-                    repo.delete_image(conn, old_news.image_id).await?;
+                if updated_news.image_id != old_news.image_id {
+                    changes.release_image(conn, old_news.image_id).await?;
                 }
 
-                Ok::<_, Error>(updated_news)
+                Ok::<_, ApiError>(updated_news)
             })
         })
-        .await
-        .map_err(|e| server_error(e.into()))?;
+        .await;
 
-    Ok(json!(result))
+    changes.finish(result).await
 }
 
 #[rocket::delete("/news/<id>")]
-pub async fn delete_news<'a>(
+pub async fn delete_news(
     mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
     id: i32,
-) -> Result<rocket::response::status::NoContent, Custom<Value>> {
-    let repo = ImageRepository::new()
-        .await
-        .map_err(|e| server_error(e.into()))?;
+) -> Result<rocket::response::status::NoContent, ApiError> {
+    let mut changes = ImageChanges::new(storage);
 
-    db.build_transaction()
+    let result = db
+        .build_transaction()
         .run(|conn| {
+            let changes = &mut changes;
             Box::pin(async move {
                 let news = NewsRepository::find(conn, id).await?;
-                let image_id = news.image_id;
 
                 NewsRepository::delete(conn, id).await?;
+                changes.release_image(conn, news.image_id).await?;
 
-                repo.delete_image(conn, image_id).await?;
-
-                Ok::<_, Error>(())
+                Ok::<_, ApiError>(())
             })
         })
+        .await;
+
+    changes
+        .finish(result)
         .await
         .map(|_| rocket::response::status::NoContent)
-        .map_err(|e| server_error(e.into()))
 }
@@ -1,104 +1,181 @@
-use crate::errors::server_error;
-use crate::models::program::{NewProgram, UpdateProgram};
-use crate::repositories::image::ImageRepository;
+use std::sync::Arc;
+
+use crate::errors::ApiError;
+use crate::models::program::{NewProgram, Program, UpdateProgram};
+use crate::repositories::image::ImageChanges;
 use crate::repositories::program::ProgramRepository;
 use crate::utils::form_data::{FormData, FromFormData};
-use crate::utils::form_fields::FormConfig;
+use crate::utils::form_fields::{FormConfig, ImageFormData};
+use crate::utils::storage::StorageBackend;
 
-use super::DbConn;
+use super::{AdminUser, DbConn};
 
+use diesel_async::AsyncPgConnection;
 use rocket::http::ContentType;
-use rocket::response::status::{Custom, NoContent};
-use rocket::serde::json::{json, Value};
+use rocket::response::status::NoContent;
+use rocket::serde::json::serde_json::Map;
+use rocket::serde::json::{json, Json, Value};
 use rocket::Data;
+use rocket::State;
 use rocket_db_pools::Connection;
 
 #[rocket::get("/programs/<event_id>")]
-pub async fn get_programs_for_event<'a>(
+pub async fn get_programs_for_event(
     mut db: Connection<DbConn>,
     event_id: i32,
-) -> Result<Value, Custom<Value>> {
+) -> Result<Value, ApiError> {
     ProgramRepository::find_program_for_event(&mut db, event_id)
         .await
         .map(|programs| json!(programs))
-        .map_err(|e| server_error(e.into()))
+        .map_err(ApiError::from)
 }
 
 #[rocket::delete("/programs/<id>")]
-pub async fn delete_program<'a>(
+pub async fn delete_program(
     mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
     id: i32,
-) -> Result<NoContent, Custom<Value>> {
-    ProgramRepository::delete(&mut db, id)
-        .await
-        .map(|_| NoContent)
-        .map_err(|e| server_error(e.into()))
+) -> Result<NoContent, ApiError> {
+    let mut changes = ImageChanges::new(storage);
+
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                let program = ProgramRepository::find(conn, id).await?;
+
+                ProgramRepository::delete(conn, id).await?;
+                changes.release_image(conn, program.image_id).await?;
+
+                Ok::<_, ApiError>(())
+            })
+        })
+        .await;
+
+    changes.finish(result).await.map(|_| NoContent)
 }
 
 #[rocket::post("/programs", format = "multipart/form-data", data = "<data>")]
 pub async fn create_program<'a>(
     mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
     content_type: &'a ContentType,
     data: Data<'a>,
-) -> Result<Value, Custom<Value>> {
+) -> Result<Value, ApiError> {
     let config = FormConfig::<NewProgram>::new();
 
     let form_data = FormData::from_multipart(content_type, data, &config).await?;
 
-    let repo = ImageRepository::new()
-        .await
-        .map_err(|e| server_error(e.into()))?;
+    let image_field = form_data.image_field.clone();
+    let mut new_program = NewProgram::from_form_data(form_data)?;
+
+    let mut changes = ImageChanges::new(storage);
 
-    let image_id = if let Some(image_data) = form_data.image_field.clone() {
-        let image = repo
-            .save_image(&mut db, image_data)
-            .await
-            .map_err(|e| server_error(e.into()))?;
-        Some(image.id)
-    } else {
-        None
-    };
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                if let Some(image_data) = image_field {
+                    new_program.image_id = changes.save_image(conn, image_data).await?.id;
+                }
 
-    let mut new_program = NewProgram::from_form_data(form_data).unwrap();
-    new_program.image_id = image_id.unwrap_or_default();
+                Ok::<_, ApiError>(
+                    ProgramRepository::create_program_for_event(conn, new_program).await?,
+                )
+            })
+        })
+        .await;
+
+    changes.finish(result).await.map(|program| json!(program))
+}
+
+#[rocket::post("/programs", format = "json", data = "<body>")]
+pub async fn create_program_json(
+    mut db: Connection<DbConn>,
+    _admin: AdminUser,
+    body: Json<Map<String, Value>>,
+) -> Result<Value, ApiError> {
+    let form_data = FormData::from_json::<NewProgram>(body.into_inner())?;
+    let new_program = NewProgram::from_form_data(form_data)?;
 
     ProgramRepository::create_program_for_event(&mut db, new_program)
         .await
-        .map(|event| json!(event))
-        .map_err(|e| server_error(e.into()))
+        .map(|program| json!(program))
+        .map_err(ApiError::from)
 }
 
 #[rocket::put("/programs/<id>", format = "multipart/form-data", data = "<data>")]
 pub async fn update_program<'a>(
     mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
     id: i32,
     content_type: &'a ContentType,
     data: Data<'a>,
-) -> Result<Value, Custom<Value>> {
+) -> Result<Value, ApiError> {
     let config = FormConfig::<UpdateProgram>::new();
     let form_data = FormData::from_multipart(content_type, data, &config).await?;
 
-    let repo = ImageRepository::new()
-        .await
-        .map_err(|e| server_error(e.into()))?;
-
-    // erase the old image
+    let image_field = form_data.image_field.clone();
+    let update_program = UpdateProgram::from_form_data(form_data)?;
 
-    let image_id = if let Some(image_data) = form_data.image_field.clone() {
-        let image = repo
-            .save_image(&mut db, image_data)
-            .await
-            .map_err(|e| server_error(e.into()))?;
-        Some(image.id)
-    } else {
-        None
-    };
+    update(&mut db, storage, id, update_program, image_field)
+        .await
+        .map(|program| json!(program))
+}
 
-    let mut update_program = UpdateProgram::from_form_data(form_data)?;
-    update_program.image_id = image_id;
+#[rocket::put("/programs/<id>", format = "json", data = "<body>")]
+pub async fn update_program_json(
+    mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    _admin: AdminUser,
+    id: i32,
+    body: Json<Map<String, Value>>,
+) -> Result<Value, ApiError> {
+    let form_data = FormData::from_json::<UpdateProgram>(body.into_inner())?;
+    let update_program = UpdateProgram::from_form_data(form_data)?;
 
-    ProgramRepository::update(&mut db, id, update_program)
+    update(&mut db, storage, id, update_program, None)
         .await
-        .map(|event| json!(event))
-        .map_err(|e| server_error(e.into()))
+        .map(|program| json!(program))
+}
+
+/// Updates the program with `image_field` saved as its new image, if given, and
+/// releases the image it used before when that changed.
+async fn update(
+    db: &mut AsyncPgConnection,
+    storage: &Arc<dyn StorageBackend>,
+    id: i32,
+    mut update_program: UpdateProgram,
+    image_field: Option<ImageFormData>,
+) -> Result<Program, ApiError> {
+    let mut changes = ImageChanges::new(storage);
+
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                let old_program = ProgramRepository::find(conn, id).await?;
+
+                if let Some(image_data) = image_field {
+                    update_program.image_id = Some(changes.save_image(conn, image_data).await?.id);
+                }
+
+                let updated_program = ProgramRepository::update(conn, id, update_program).await?;
+
+                if updated_program.image_id != old_program.image_id {
+                    changes.release_image(conn, old_program.image_id).await?;
+                }
+
+                Ok::<_, ApiError>(updated_program)
+            })
+        })
+        .await;
+
+    changes.finish(result).await
 }
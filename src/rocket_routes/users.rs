@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use crate::errors::ApiError;
+use crate::models::user::{UpdateProfile, UserRole};
+use crate::repositories::image::ImageChanges;
+use crate::repositories::session::SessionRepository;
+use crate::repositories::user::UserRepository;
+use crate::utils::form_data::{FormData, FromFormData};
+use crate::utils::form_fields::FormConfig;
+use crate::utils::storage::StorageBackend;
+use crate::utils::validation::Validate;
+
+use rocket::http::ContentType;
+use rocket::response::status::NoContent;
+use rocket::serde::json::{json, Json, Value};
+use rocket::Data;
+use rocket::State;
+use rocket_db_pools::Connection;
+
+use super::{AuthenticatedUser, DbConn};
+
+#[rocket::get("/users/me")]
+pub async fn get_me(user: AuthenticatedUser) -> Value {
+    json!(user.0)
+}
+
+#[rocket::get("/users/me/sessions")]
+pub async fn get_my_sessions(
+    mut db: Connection<DbConn>,
+    user: AuthenticatedUser,
+) -> Result<Value, ApiError> {
+    let AuthenticatedUser(user, current_session_id) = user;
+
+    SessionRepository::find_active_for_user(&mut db, user.id)
+        .await
+        .map(|sessions| {
+            json!(sessions
+                .into_iter()
+                .map(|session| {
+                    let current = session.id == current_session_id;
+                    let mut session = json!(session);
+                    session["current"] = json!(current);
+                    session
+                })
+                .collect::<Vec<_>>())
+        })
+        .map_err(ApiError::from)
+}
+
+#[rocket::delete("/users/me/sessions/<id>")]
+pub async fn revoke_my_session(
+    mut db: Connection<DbConn>,
+    user: AuthenticatedUser,
+    id: i32,
+) -> Result<NoContent, ApiError> {
+    match SessionRepository::revoke(&mut db, id, user.0.id).await {
+        Ok(0) => Err(ApiError::NotFound("Session not found".to_string())),
+        Ok(_) => Ok(NoContent),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[rocket::put("/users/me", format = "multipart/form-data", data = "<data>")]
+pub async fn update_me<'a>(
+    mut db: Connection<DbConn>,
+    storage: &State<Arc<dyn StorageBackend>>,
+    user: AuthenticatedUser,
+    content_type: &'a ContentType,
+    data: Data<'a>,
+) -> Result<Value, ApiError> {
+    let config = FormConfig::<UpdateProfile>::new();
+    let form_data = FormData::from_multipart(content_type, data, &config).await?;
+
+    let image_field = form_data.image_field.clone();
+    let mut profile = UpdateProfile::from_form_data(form_data)?;
+
+    let id = user.0.id;
+    let old_image_id = user.0.image_id;
+
+    let mut changes = ImageChanges::new(storage);
+
+    let result = db
+        .build_transaction()
+        .run(|conn| {
+            let changes = &mut changes;
+            Box::pin(async move {
+                if let Some(image_data) = image_field {
+                    profile.image_id = Some(changes.save_image(conn, image_data).await?.id);
+                }
+
+                let updated_user = UserRepository::update_profile(conn, id, profile).await?;
+
+                if let Some(old_image_id) = old_image_id {
+                    if updated_user.image_id != Some(old_image_id) {
+                        changes.release_image(conn, old_image_id).await?;
+                    }
+                }
+
+                Ok::<_, ApiError>(updated_user)
+            })
+        })
+        .await;
+
+    changes.finish(result).await.map(|user| json!(user))
+}
+
+#[rocket::put("/users/<id>", format = "json", data = "<profile>")]
+pub async fn update_user(
+    mut db: Connection<DbConn>,
+    id: i32,
+    user: AuthenticatedUser,
+    profile: Json<UpdateProfile>,
+) -> Result<Value, ApiError> {
+    if user.0.id != id && user.0.role != UserRole::Admin {
+        return Err(ApiError::Forbidden(
+            "You can only edit your own profile".to_string(),
+        ));
+    }
+    profile.validate()?;
+
+    UserRepository::update_profile(&mut db, id, profile.into_inner())
+        .await
+        .map(|user| json!(user))
+        .map_err(ApiError::from)
+}
@@ -1,5 +1,17 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    email_verification_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     events (id) {
         id -> Int4,
@@ -30,6 +42,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    image_variants (id) {
+        id -> Int4,
+        image_id -> Int4,
+        #[max_length = 32]
+        name -> Varchar,
+        #[max_length = 16]
+        format -> Varchar,
+        image_url -> Text,
+        image_key -> Text,
+        width -> Int4,
+        height -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     images (id) {
         id -> Int4,
@@ -39,6 +67,7 @@ diesel::table! {
         height -> Int4,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        in_library -> Bool,
     }
 }
 
@@ -54,6 +83,18 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    password_reset_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        expires_at -> Timestamp,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     programs (id) {
         id -> Int4,
@@ -67,6 +108,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    sessions (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 64]
+        refresh_token_hash -> Varchar,
+        user_agent -> Nullable<Text>,
+        #[max_length = 64]
+        ip_address -> Nullable<Varchar>,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+        last_used_at -> Timestamp,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Int4,
@@ -89,24 +146,35 @@ diesel::table! {
         image_id -> Nullable<Int4>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        disabled_at -> Nullable<Timestamp>,
+        email_verified_at -> Nullable<Timestamp>,
+        locked_until -> Nullable<Timestamp>,
     }
 }
 
+diesel::joinable!(email_verification_tokens -> users (user_id));
 diesel::joinable!(galleries -> images (featured_image_id));
 diesel::joinable!(gallery_images -> galleries (gallery_id));
 diesel::joinable!(gallery_images -> images (image_id));
+diesel::joinable!(image_variants -> images (image_id));
 diesel::joinable!(news -> images (image_id));
+diesel::joinable!(password_reset_tokens -> users (user_id));
 diesel::joinable!(programs -> events (event_id));
 diesel::joinable!(programs -> images (image_id));
+diesel::joinable!(sessions -> users (user_id));
 diesel::joinable!(users -> events (event_id));
 diesel::joinable!(users -> images (image_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    email_verification_tokens,
     events,
     galleries,
     gallery_images,
+    image_variants,
     images,
     news,
+    password_reset_tokens,
     programs,
+    sessions,
     users,
 );
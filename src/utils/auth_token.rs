@@ -0,0 +1,96 @@
+use data_encoding::BASE64URL_NOPAD;
+use ring::hmac;
+
+/// Access tokens are short-lived; clients renew them with the session's refresh token.
+pub const ACCESS_TOKEN_TTL_SECONDS: i64 = 15 * 60;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AccessClaims {
+    pub user_id: i32,
+    pub session_id: i32,
+}
+
+/// Issues and verifies HMAC-signed bearer tokens of the form
+/// `<user_id>.<session_id>.<expires_at>.<signature>`.
+pub struct TokenSigner {
+    key: hmac::Key,
+}
+
+impl TokenSigner {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, secret),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        let secret = std::env::var("AUTH_TOKEN_SECRET")
+            .map_err(|e| format!("AUTH_TOKEN_SECRET not set: {}", e))?;
+        if secret.len() < 32 {
+            return Err("AUTH_TOKEN_SECRET must be at least 32 characters long".to_string());
+        }
+        Ok(Self::new(secret.as_bytes()))
+    }
+
+    pub fn issue(&self, user_id: i32, session_id: i32) -> String {
+        let expires_at = chrono::Utc::now().timestamp() + ACCESS_TOKEN_TTL_SECONDS;
+        self.sign(user_id, session_id, expires_at)
+    }
+
+    /// Returns the claims of a token with a valid signature that has not expired yet.
+    pub fn verify(&self, token: &str) -> Option<AccessClaims> {
+        let (payload, signature) = token.rsplit_once('.')?;
+        let signature = BASE64URL_NOPAD.decode(signature.as_bytes()).ok()?;
+        hmac::verify(&self.key, payload.as_bytes(), &signature).ok()?;
+
+        let mut parts = payload.split('.');
+        let user_id = parts.next()?.parse().ok()?;
+        let session_id = parts.next()?.parse().ok()?;
+        let expires_at: i64 = parts.next()?.parse().ok()?;
+        if parts.next().is_some() || expires_at <= chrono::Utc::now().timestamp() {
+            return None;
+        }
+
+        Some(AccessClaims {
+            user_id,
+            session_id,
+        })
+    }
+
+    fn sign(&self, user_id: i32, session_id: i32, expires_at: i64) -> String {
+        let payload = format!("{}.{}.{}", user_id, session_id, expires_at);
+        let signature = hmac::sign(&self.key, payload.as_bytes());
+        format!("{}.{}", payload, BASE64URL_NOPAD.encode(signature.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_verification() {
+        let signer = TokenSigner::new(b"test_secret_that_is_long_enough_!");
+        let token = signer.issue(42, 7);
+        assert_eq!(
+            signer.verify(&token),
+            Some(AccessClaims {
+                user_id: 42,
+                session_id: 7
+            })
+        );
+
+        let other_signer = TokenSigner::new(b"another_secret_that_is_long_enough");
+        assert_eq!(other_signer.verify(&token), None);
+
+        let tampered = token.replacen("42.", "43.", 1);
+        assert_eq!(signer.verify(&tampered), None);
+    }
+
+    #[test]
+    fn test_expired_token() {
+        let signer = TokenSigner::new(b"test_secret_that_is_long_enough_!");
+        let token = signer.sign(42, 7, chrono::Utc::now().timestamp() - 1);
+        assert_eq!(signer.verify(&token), None);
+    }
+}
@@ -1,11 +1,13 @@
 use rocket::http::ContentType;
-use rocket::{response::status::Custom, Data};
+use rocket::serde::json::serde_json::Map;
+use rocket::serde::json::Value;
+use rocket::Data;
 use rocket_multipart_form_data::MultipartFormData;
-use serde_json::Value;
 use std::collections::HashMap;
 
-use crate::errors::bad_request_error;
+use crate::errors::ApiError;
 use crate::utils::form_fields::*;
+use crate::utils::validation::Validator;
 
 #[derive(Debug)]
 pub struct FormData {
@@ -13,130 +15,265 @@ pub struct FormData {
     pub optional_text_values: HashMap<String, Option<String>>,
     pub required_number_values: HashMap<String, i32>,
     pub optional_number_values: HashMap<String, Option<i32>>,
+    /// The uploaded file of single-image forms.
     pub image_field: Option<ImageFormData>,
+    /// Every uploaded file of forms accepting several, see `FormFields::max_images`.
+    pub image_fields: Vec<ImageFormData>,
+    /// An already uploaded image referenced by a JSON body. Always `None` for multipart
+    /// forms, whose image the handler saves and sets on the model itself.
+    pub image_id: Option<i32>,
 }
 
 impl FormData {
+    /// Parses the multipart body and checks it against `T`'s fields and rules.
+    /// Every missing or invalid field is reported at once.
     pub async fn from_multipart<T: FormFields>(
         content_type: &ContentType,
         data: Data<'_>,
         config: &FormConfig<'_, T>,
-    ) -> Result<Self, Custom<Value>> {
+    ) -> Result<Self, ApiError> {
         let form = MultipartFormData::parse(content_type, data, config.get_options())
             .await
-            .map_err(|e| bad_request_error(e.into()))?;
+            .map_err(|e| ApiError::Validation(e.to_string()))?;
 
+        let mut validator = Validator::new();
         let mut required_text_values = HashMap::new();
         let mut optional_text_values = HashMap::new();
         let mut required_number_values = HashMap::new();
         let mut optional_number_values = HashMap::new();
 
         for field_name in T::get_required_text_fields() {
-            let value = Self::get_required_text_field(&form, field_name)?;
-            required_text_values.insert(field_name.to_string(), value);
+            match Self::get_text_field(&form, field_name) {
+                Some(value) => {
+                    required_text_values.insert(field_name.to_string(), value);
+                }
+                None => validator.error(field_name, "is required"),
+            }
         }
 
         for field_name in T::get_optional_text_fields() {
-            let value = Self::get_optional_text_field(&form, field_name)?;
+            let value = Self::get_text_field(&form, field_name);
             optional_text_values.insert(field_name.to_string(), value);
         }
 
         for field_name in T::get_required_number_fields() {
-            let value = Self::get_required_number_field(&form, field_name)?;
-            required_number_values.insert(field_name.to_string(), value);
+            match Self::get_text_field(&form, field_name).map(|value| value.parse()) {
+                Some(Ok(value)) => {
+                    required_number_values.insert(field_name.to_string(), value);
+                }
+                Some(Err(_)) => validator.error(field_name, "must be a whole number"),
+                None => validator.error(field_name, "is required"),
+            }
         }
 
         for field_name in T::get_optional_number_fields() {
-            let value = Self::get_optional_number_field(&form, field_name)?;
-            optional_number_values.insert(field_name.to_string(), value);
+            match Self::get_text_field(&form, field_name).filter(|value| !value.is_empty()) {
+                Some(value) => match value.parse() {
+                    Ok(value) => {
+                        optional_number_values.insert(field_name.to_string(), Some(value));
+                    }
+                    Err(_) => validator.error(field_name, "must be a whole number"),
+                },
+                None => {
+                    optional_number_values.insert(field_name.to_string(), None);
+                }
+            }
         }
 
-        let image_field = if T::has_image() {
-            match Self::get_image_field(&form) {
-                Ok(image) => Some(image),
-                Err(e) if T::is_image_required() => return Err(e),
-                Err(_) => None,
-            }
+        let mut image_fields = if T::has_image() {
+            Self::get_image_fields(&form)
+        } else {
+            vec![]
+        };
+        if image_fields.is_empty() && T::has_image() && T::is_image_required() {
+            validator.error("image", "is required");
+        }
+        if image_fields.len() > T::max_images() as usize {
+            validator.error(
+                "image",
+                format!("accepts at most {} files", T::max_images()),
+            );
+        }
+        let image_field = if T::max_images() == 1 {
+            image_fields.pop()
         } else {
             None
         };
 
-        Ok(Self {
+        let form_data = Self {
             required_text_values,
             optional_text_values,
             required_number_values,
             optional_number_values,
             image_field,
-        })
+            image_fields,
+            image_id: None,
+        };
+
+        form_data.check_rules::<T>(&mut validator);
+        validator.finish()?;
+
+        Ok(form_data)
     }
 
-    fn get_required_text_field(
-        form: &MultipartFormData,
-        field_name: &'static str,
-    ) -> Result<String, Custom<Value>> {
-        form.texts
-            .get(field_name)
-            .and_then(|fields| fields.first())
-            .map(|field| field.text.clone())
-            .ok_or_else(|| {
-                bad_request_error(format!("Missing required field: {}", field_name).into())
-            })
+    /// Reads the same fields as `from_multipart` from a JSON object, with the image given
+    /// by the id of an existing row instead of a file.
+    pub fn from_json<T: FormFields>(body: Map<String, Value>) -> Result<Self, ApiError> {
+        let mut validator = Validator::new();
+        let mut required_text_values = HashMap::new();
+        let mut optional_text_values = HashMap::new();
+        let mut required_number_values = HashMap::new();
+        let mut optional_number_values = HashMap::new();
+
+        for field_name in T::get_required_text_fields() {
+            match Self::get_json_text(&body, field_name) {
+                Ok(Some(value)) => {
+                    required_text_values.insert(field_name.to_string(), value);
+                }
+                Ok(None) => validator.error(field_name, "is required"),
+                Err(message) => validator.error(field_name, message),
+            }
+        }
+
+        for field_name in T::get_optional_text_fields() {
+            match Self::get_json_text(&body, field_name) {
+                Ok(value) => {
+                    optional_text_values.insert(field_name.to_string(), value);
+                }
+                Err(message) => validator.error(field_name, message),
+            }
+        }
+
+        for field_name in T::get_required_number_fields() {
+            match Self::get_json_number(&body, field_name) {
+                Ok(Some(value)) => {
+                    required_number_values.insert(field_name.to_string(), value);
+                }
+                Ok(None) => validator.error(field_name, "is required"),
+                Err(message) => validator.error(field_name, message),
+            }
+        }
+
+        for field_name in T::get_optional_number_fields() {
+            match Self::get_json_number(&body, field_name) {
+                Ok(value) => {
+                    optional_number_values.insert(field_name.to_string(), value);
+                }
+                Err(message) => validator.error(field_name, message),
+            }
+        }
+
+        let image_id = if T::has_image() {
+            let field_name = T::image_id_field();
+            match Self::get_json_number(&body, field_name) {
+                Ok(None) if T::is_image_required() => {
+                    validator.error(field_name, "is required");
+                    None
+                }
+                Ok(image_id) => image_id,
+                Err(message) => {
+                    validator.error(field_name, message);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let form_data = Self {
+            required_text_values,
+            optional_text_values,
+            required_number_values,
+            optional_number_values,
+            image_field: None,
+            image_fields: vec![],
+            image_id,
+        };
+
+        form_data.check_rules::<T>(&mut validator);
+        validator.finish()?;
+
+        Ok(form_data)
     }
 
-    fn get_optional_text_field(
-        form: &MultipartFormData,
-        field_name: &'static str,
-    ) -> Result<Option<String>, Custom<Value>> {
-        Ok(form
-            .texts
+    /// Applies `T`'s rules to the values present, in the order the fields are declared.
+    fn check_rules<T: FormFields>(&self, validator: &mut Validator) {
+        let text_fields = T::get_required_text_fields()
+            .into_iter()
+            .chain(T::get_optional_text_fields());
+        for field_name in text_fields {
+            let value = self
+                .required_text_values
+                .get(field_name)
+                .or_else(|| self.optional_text_values.get(field_name)?.as_ref());
+            if let Some(value) = value {
+                validator.text(field_name, value, T::get_rules_for(field_name));
+            }
+        }
+
+        let number_fields = T::get_required_number_fields()
+            .into_iter()
+            .chain(T::get_optional_number_fields());
+        for field_name in number_fields {
+            let value = self
+                .required_number_values
+                .get(field_name)
+                .or_else(|| self.optional_number_values.get(field_name)?.as_ref());
+            if let Some(value) = value {
+                validator.number(field_name, *value, T::get_rules_for(field_name));
+            }
+        }
+    }
+
+    fn get_text_field(form: &MultipartFormData, field_name: &'static str) -> Option<String> {
+        form.texts
             .get(field_name)
             .and_then(|fields| fields.first())
-            .map(|field| field.text.clone()))
+            .map(|field| field.text.clone())
     }
 
-    fn get_required_number_field(
-        form: &MultipartFormData,
-        field_name: &'static str,
-    ) -> Result<i32, Custom<Value>> {
-        Self::get_required_text_field(form, field_name)?
-            .parse()
-            .map_err(|e| bad_request_error(format!("Invalid {}: {}", field_name, e).into()))
+    fn get_json_text(
+        body: &Map<String, Value>,
+        field_name: &str,
+    ) -> Result<Option<String>, &'static str> {
+        match body.get(field_name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err("must be a string"),
+        }
     }
 
-    fn get_optional_number_field(
-        form: &MultipartFormData,
-        field_name: &'static str,
-    ) -> Result<Option<i32>, Custom<Value>> {
-        match form.texts.get(field_name) {
-            Some(fields) if !fields.is_empty() => {
-                let value = fields[0].text.clone();
-                if value.is_empty() {
-                    Ok(None)
-                } else {
-                    value.parse().map(Some).map_err(|e| {
-                        bad_request_error(format!("Invalid {}: {}", field_name, e).into())
-                    })
-                }
-            }
-            _ => Ok(None),
+    fn get_json_number(
+        body: &Map<String, Value>,
+        field_name: &str,
+    ) -> Result<Option<i32>, &'static str> {
+        match body.get(field_name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => value
+                .as_i64()
+                .and_then(|value| i32::try_from(value).ok())
+                .map(Some)
+                .ok_or("must be a whole number"),
         }
     }
 
-    fn get_image_field(form: &MultipartFormData) -> Result<ImageFormData, Custom<Value>> {
-        let field = form
-            .raw
+    fn get_image_fields(form: &MultipartFormData) -> Vec<ImageFormData> {
+        form.raw
             .get("image")
-            .and_then(|fields| fields.first())
-            .ok_or_else(|| bad_request_error("Missing: image".into()))?;
-
-        Ok(ImageFormData {
-            raw_data: field.raw.clone(),
-            file_name: field.file_name.clone(),
-            content_type: field.content_type.clone(),
-        })
+            .map(|fields| {
+                fields
+                    .iter()
+                    .map(|field| ImageFormData {
+                        raw_data: field.raw.clone(),
+                        file_name: field.file_name.clone(),
+                        content_type: field.content_type.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 }
 
 pub trait FromFormData: Sized {
-    fn from_form_data(form_data: FormData) -> Result<Self, Custom<Value>>;
+    fn from_form_data(form_data: FormData) -> Result<Self, ApiError>;
 }
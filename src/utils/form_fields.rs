@@ -1,7 +1,11 @@
 use rocket_multipart_form_data::mime::Mime;
-use rocket_multipart_form_data::{mime, MultipartFormDataField, MultipartFormDataOptions};
+use rocket_multipart_form_data::{
+    mime, MultipartFormDataField, MultipartFormDataOptions, Repetition,
+};
 
-const MAX_IMAGE_SIZE: u64 = 30 * 1024 * 1024;
+use crate::utils::validation::Rule;
+
+pub const MAX_IMAGE_SIZE: u64 = 30 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct ImageFormData {
@@ -29,12 +33,37 @@ pub trait FormFields {
     fn is_image_required() -> bool {
         true
     }
+    /// How many `image` parts one request may carry. Requests with more are rejected.
+    fn max_images() -> u32 {
+        1
+    }
+    /// The JSON key holding the id of an existing image, used instead of an upload.
+    fn image_id_field() -> &'static str {
+        "image_id"
+    }
+    /// Validation rules per field name, checked for every value the request contains.
+    fn get_field_rules() -> Vec<(&'static str, &'static [Rule])> {
+        vec![]
+    }
+    fn get_rules_for(field_name: &str) -> &'static [Rule] {
+        Self::get_field_rules()
+            .into_iter()
+            .find(|(name, _)| *name == field_name)
+            .map(|(_, rules)| rules)
+            .unwrap_or(&[])
+    }
 }
 
 pub struct FormConfig<'a, T: FormFields> {
     phantom: std::marker::PhantomData<(&'a (), T)>,
 }
 
+impl<T: FormFields> Default for FormConfig<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a, T: FormFields> FormConfig<'a, T> {
     pub fn new() -> Self {
         Self {
@@ -50,7 +79,9 @@ impl<'a, T: FormFields> FormConfig<'a, T> {
                 MultipartFormDataField::raw("image")
                     .size_limit(MAX_IMAGE_SIZE)
                     .content_type_by_string(Some(mime::IMAGE_STAR))
-                    .unwrap(),
+                    .unwrap()
+                    // One over the limit, so that going over it can be reported
+                    .repetition(Repetition::fixed(T::max_images() + 1)),
             );
         }
 
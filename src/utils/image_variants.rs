@@ -0,0 +1,142 @@
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+
+/// Widths the variants are scaled down to, smallest first. Heights keep the aspect ratio.
+pub const VARIANT_WIDTHS: [(&str, u32); 3] = [("thumbnail", 320), ("medium", 800), ("large", 1600)];
+
+const JPEG_QUALITY: u8 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantFormat {
+    Jpeg,
+    /// Lossless, the only WebP encoding the `image` crate offers.
+    WebP,
+}
+
+impl VariantFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VariantFormat::Jpeg => "jpeg",
+            VariantFormat::WebP => "webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VariantFormat::Jpeg => "jpg",
+            VariantFormat::WebP => "webp",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            VariantFormat::Jpeg => "image/jpeg",
+            VariantFormat::WebP => "image/webp",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct EncodedVariant {
+    pub name: &'static str,
+    pub format: VariantFormat,
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Encodes every size in `VARIANT_WIDTHS` narrower than `img` as JPEG and WebP. There is
+/// always a thumbnail; an image narrower than it is only re-encoded. Slow for large
+/// photos, so call it off the async runtime.
+pub fn generate_variants(img: &DynamicImage) -> Result<Vec<EncodedVariant>, String> {
+    let mut variants = Vec::new();
+
+    for (index, (name, width)) in VARIANT_WIDTHS.into_iter().enumerate() {
+        if width >= img.width() && index > 0 {
+            break;
+        }
+
+        let resized = if width < img.width() {
+            img.resize(width, u32::MAX, FilterType::CatmullRom)
+        } else {
+            img.clone()
+        };
+
+        for format in [VariantFormat::Jpeg, VariantFormat::WebP] {
+            variants.push(EncodedVariant {
+                name,
+                format,
+                data: encode(&resized, format)?,
+                width: resized.width(),
+                height: resized.height(),
+            });
+        }
+    }
+
+    Ok(variants)
+}
+
+fn encode(img: &DynamicImage, format: VariantFormat) -> Result<Vec<u8>, String> {
+    let mut data = Cursor::new(Vec::new());
+
+    match format {
+        VariantFormat::Jpeg => JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY)
+            .encode_image(&img.to_rgb8())
+            .map_err(|e| e.to_string())?,
+        VariantFormat::WebP => {
+            let img = if img.color().has_alpha() {
+                DynamicImage::ImageRgba8(img.to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(img.to_rgb8())
+            };
+            img.write_to(&mut data, ImageFormat::WebP)
+                .map_err(|e| e.to_string())?
+        }
+    }
+
+    Ok(data.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variants_narrower_than_the_original() {
+        let img = DynamicImage::new_rgb8(1000, 500);
+
+        let variants = generate_variants(&img).unwrap();
+
+        let sizes: Vec<_> = variants
+            .iter()
+            .map(|v| (v.name, v.format.name(), v.width, v.height))
+            .collect();
+        assert_eq!(
+            sizes,
+            vec![
+                ("thumbnail", "jpeg", 320, 160),
+                ("thumbnail", "webp", 320, 160),
+                ("medium", "jpeg", 800, 400),
+                ("medium", "webp", 800, 400),
+            ]
+        );
+
+        let webp = image::load_from_memory(&variants[1].data).unwrap();
+        assert_eq!((webp.width(), webp.height()), (320, 160));
+    }
+
+    #[test]
+    fn test_small_image_only_gets_a_thumbnail() {
+        let img = DynamicImage::new_rgba8(100, 80);
+
+        let variants = generate_variants(&img).unwrap();
+
+        assert_eq!(variants.len(), 2);
+        assert!(variants
+            .iter()
+            .all(|v| v.name == "thumbnail" && v.width == 100));
+    }
+}
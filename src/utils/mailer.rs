@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[rocket::async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), String>;
+}
+
+/// Writes emails to the application log instead of delivering them.
+pub struct LogMailer;
+
+#[rocket::async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), String> {
+        rocket::info!(
+            "Email to {}\nSubject: {}\n\n{}",
+            email.to,
+            email.subject,
+            email.body
+        );
+        Ok(())
+    }
+}
+
+/// Keeps sent emails in memory so tests can inspect them.
+#[derive(Default)]
+pub struct MemoryMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl MemoryMailer {
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+#[rocket::async_trait]
+impl Mailer for MemoryMailer {
+    async fn send(&self, email: Email) -> Result<(), String> {
+        self.sent.lock().unwrap().push(email);
+        Ok(())
+    }
+}
+
+/// Stores every email as a file in `outbox_dir`, for local testing. The recipient is only
+/// written into the file, as it may contain anything `is_email` accepts, `/` included.
+pub struct FileMailer {
+    outbox_dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(outbox_dir: PathBuf) -> Self {
+        Self { outbox_dir }
+    }
+}
+
+#[rocket::async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), String> {
+        tokio::fs::create_dir_all(&self.outbox_dir)
+            .await
+            .map_err(|e| format!("Cannot create outbox: {}", e))?;
+
+        let path = self
+            .outbox_dir
+            .join(format!("{}.eml", uuid::Uuid::new_v4()));
+        let content = format!(
+            "To: {}\nSubject: {}\n\n{}\n",
+            email.to, email.subject, email.body
+        );
+
+        tokio::fs::write(&path, content)
+            .await
+            .map_err(|e| format!("Cannot write email: {}", e))
+    }
+}
+
+/// Picks the mailer from `MAILER`: `log` (default), `memory`, or `file` with `MAILER_OUTBOX_DIR`.
+pub fn mailer_from_env() -> Result<Box<dyn Mailer>, String> {
+    match std::env::var("MAILER").as_deref() {
+        Ok("log") | Err(_) => Ok(Box::new(LogMailer)),
+        Ok("memory") => Ok(Box::new(MemoryMailer::default())),
+        Ok("file") => {
+            let outbox_dir =
+                std::env::var("MAILER_OUTBOX_DIR").unwrap_or_else(|_| "mail_outbox".to_string());
+            Ok(Box::new(FileMailer::new(outbox_dir.into())))
+        }
+        Ok(other) => Err(format!("Unknown MAILER: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rocket::async_test]
+    async fn test_memory_mailer() {
+        let mailer = MemoryMailer::default();
+        let email = Email {
+            to: "seller@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "Welcome".to_string(),
+        };
+
+        mailer.send(email).await.unwrap();
+
+        let sent = mailer.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "seller@example.com");
+    }
+
+    #[rocket::async_test]
+    async fn test_file_mailer_stays_in_outbox() {
+        let root = std::env::temp_dir().join(format!("mailer-{}", uuid::Uuid::new_v4()));
+        let outbox_dir = root.join("outbox");
+        let mailer = FileMailer::new(outbox_dir.clone());
+        let email = Email {
+            to: "../../seller@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "Welcome".to_string(),
+        };
+
+        mailer.send(email).await.unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(&outbox_dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let content = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        assert!(content.starts_with("To: ../../seller@example.com\n"));
+        assert_eq!(std::fs::read_dir(&root).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,4 +1,11 @@
+pub mod auth_token;
 pub mod form_data;
 pub mod form_fields;
+pub mod image_variants;
+pub mod mailer;
 pub mod password_hashing;
+pub mod rate_limiter;
 pub mod s3_storage;
+pub mod secure_token;
+pub mod storage;
+pub mod validation;
@@ -1,20 +1,38 @@
-use data_encoding::HEXLOWER;
+use data_encoding::{BASE64_NOPAD, HEXLOWER};
 use rand::RngCore;
 use ring::pbkdf2;
 use std::num::NonZeroU32;
 
-const NUMBER_OF_ITERATIONS: u32 = 25000;
-const KEYLEN: usize = 512;
+/// Identifier of the current algorithm in the PHC string format.
+const ALGORITHM_ID: &str = "pbkdf2-sha256";
+const NUMBER_OF_ITERATIONS: u32 = 600_000;
+const KEYLEN: usize = 32;
+const SALT_LEN: usize = 16;
 
+/// A hash with the current parameters that no password matches, see `compare_dummy`.
+const DUMMY_HASH: &str =
+    "$pbkdf2-sha256$i=600000$AAAAAAAAAAAAAAAAAAAAAA$AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+
+/// Iteration count used by the legacy `hash.salt` hex format.
+const LEGACY_NUMBER_OF_ITERATIONS: u32 = 25000;
+
+/// Hashes are stored in the PHC string format, e.g.
+/// `$pbkdf2-sha256$i=600000$<base64 salt>$<base64 hash>`, so the parameters can be
+/// raised later without breaking existing passwords.
 pub struct Password;
 
+struct StoredHash {
+    iterations: NonZeroU32,
+    salt: Vec<u8>,
+    hash: Vec<u8>,
+}
+
 impl Password {
     pub fn generate_password(password: &str) -> String {
-        let mut salt = [0u8; 32];
+        let mut salt = [0u8; SALT_LEN];
         rand::thread_rng().fill_bytes(&mut salt);
-        let salt_hex = HEXLOWER.encode(&salt);
 
-        let mut hash = vec![0u8; KEYLEN];
+        let mut hash = [0u8; KEYLEN];
         pbkdf2::derive(
             pbkdf2::PBKDF2_HMAC_SHA256,
             NonZeroU32::new(NUMBER_OF_ITERATIONS).unwrap(),
@@ -23,35 +41,89 @@ impl Password {
             &mut hash,
         );
 
-        let hash_hex = HEXLOWER.encode(&hash);
-        format!("{}.{}", hash_hex, salt_hex)
+        format!(
+            "${}$i={}${}${}",
+            ALGORITHM_ID,
+            NUMBER_OF_ITERATIONS,
+            BASE64_NOPAD.encode(&salt),
+            BASE64_NOPAD.encode(&hash)
+        )
     }
 
+    /// Verifies a password against a stored hash in constant time.
     pub fn compare(stored_password: &str, supplied_password: &str) -> bool {
-        let parts: Vec<&str> = stored_password.split('.').collect();
-        if parts.len() != 2 {
-            return false;
-        }
-
-        let hash_hex = parts[0];
-        let salt_hex = parts[1];
-
-        let salt = match HEXLOWER.decode(salt_hex.as_bytes()) {
-            Ok(s) => s,
-            Err(_) => return false,
+        let stored = match Self::parse(stored_password) {
+            Some(stored) => stored,
+            None => return false,
         };
 
-        let mut hash = vec![0u8; KEYLEN];
-        pbkdf2::derive(
+        pbkdf2::verify(
             pbkdf2::PBKDF2_HMAC_SHA256,
-            NonZeroU32::new(NUMBER_OF_ITERATIONS).unwrap(),
-            &salt,
+            stored.iterations,
+            &stored.salt,
             supplied_password.as_bytes(),
-            &mut hash,
-        );
+            &stored.hash,
+        )
+        .is_ok()
+    }
+
+    /// Does the work of `compare` against a current hash when there is none to compare
+    /// with, e.g. for an unknown account. Always `false`.
+    pub fn compare_dummy(supplied_password: &str) -> bool {
+        Self::compare(DUMMY_HASH, supplied_password);
+        false
+    }
+
+    /// Whether a stored hash was created with outdated parameters and should be
+    /// replaced the next time the plain password is known.
+    pub fn needs_rehash(stored_password: &str) -> bool {
+        match Self::parse_phc(stored_password) {
+            Some(stored) => {
+                stored.iterations.get() < NUMBER_OF_ITERATIONS
+                    || stored.salt.len() < SALT_LEN
+                    || stored.hash.len() != KEYLEN
+            }
+            None => true,
+        }
+    }
+
+    fn parse(stored_password: &str) -> Option<StoredHash> {
+        Self::parse_phc(stored_password).or_else(|| Self::parse_legacy(stored_password))
+    }
+
+    fn parse_phc(stored_password: &str) -> Option<StoredHash> {
+        let mut parts = stored_password.strip_prefix('$')?.split('$');
+
+        if parts.next()? != ALGORITHM_ID {
+            return None;
+        }
+        let iterations = parts.next()?.strip_prefix("i=")?.parse().ok()?;
+        let salt = BASE64_NOPAD.decode(parts.next()?.as_bytes()).ok()?;
+        let hash = BASE64_NOPAD.decode(parts.next()?.as_bytes()).ok()?;
+        if parts.next().is_some() || hash.is_empty() {
+            return None;
+        }
+
+        Some(StoredHash {
+            iterations: NonZeroU32::new(iterations)?,
+            salt,
+            hash,
+        })
+    }
 
-        let verify_hex = HEXLOWER.encode(&hash);
-        hash_hex == verify_hex
+    fn parse_legacy(stored_password: &str) -> Option<StoredHash> {
+        let (hash_hex, salt_hex) = stored_password.split_once('.')?;
+        let hash = HEXLOWER.decode(hash_hex.as_bytes()).ok()?;
+        let salt = HEXLOWER.decode(salt_hex.as_bytes()).ok()?;
+        if hash.is_empty() {
+            return None;
+        }
+
+        Some(StoredHash {
+            iterations: NonZeroU32::new(LEGACY_NUMBER_OF_ITERATIONS).unwrap(),
+            salt,
+            hash,
+        })
     }
 }
 
@@ -59,6 +131,19 @@ impl Password {
 mod tests {
     use super::*;
 
+    fn legacy_hash(password: &str) -> String {
+        let salt = [7u8; 16];
+        let mut hash = [0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(LEGACY_NUMBER_OF_ITERATIONS).unwrap(),
+            &salt,
+            password.as_bytes(),
+            &mut hash,
+        );
+        format!("{}.{}", HEXLOWER.encode(&hash), HEXLOWER.encode(&salt))
+    }
+
     #[test]
     fn test_password_verification() {
         let password = "my_secure_password";
@@ -66,4 +151,46 @@ mod tests {
         assert!(Password::compare(&hash, password));
         assert!(!Password::compare(&hash, "wrong_password"));
     }
+
+    #[test]
+    fn test_hash_format() {
+        let hash = Password::generate_password("my_secure_password");
+        assert!(hash.starts_with("$pbkdf2-sha256$i=600000$"));
+        assert!(hash.len() <= 128, "hash must fit users.password");
+        assert!(!Password::needs_rehash(&hash));
+    }
+
+    #[test]
+    fn test_legacy_hash_verification() {
+        let hash = legacy_hash("my_secure_password");
+        assert!(Password::compare(&hash, "my_secure_password"));
+        assert!(!Password::compare(&hash, "wrong_password"));
+        assert!(Password::needs_rehash(&hash));
+    }
+
+    #[test]
+    fn test_outdated_parameters_need_rehash() {
+        let hash =
+            Password::generate_password("my_secure_password").replacen("i=600000", "i=1000", 1);
+        assert!(Password::needs_rehash(&hash));
+        assert!(!Password::compare(&hash, "my_secure_password"));
+    }
+
+    #[test]
+    fn test_dummy_hash_costs_as_much_as_a_current_one() {
+        let dummy = Password::parse(DUMMY_HASH).unwrap();
+        assert_eq!(dummy.iterations.get(), NUMBER_OF_ITERATIONS);
+        assert!(!Password::needs_rehash(DUMMY_HASH));
+        assert!(!Password::compare_dummy("password"));
+    }
+
+    #[test]
+    fn test_malformed_hash() {
+        assert!(!Password::compare("", "password"));
+        assert!(!Password::compare(
+            "$pbkdf2-sha256$i=0$AAAA$AAAA",
+            "password"
+        ));
+        assert!(!Password::compare("$argon2id$v=19$AAAA$AAAA", "password"));
+    }
 }
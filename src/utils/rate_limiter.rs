@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::models::user::normalize_email;
+
+const MAX_IP_FAILURES: u32 = 100;
+const IP_WINDOW: Duration = Duration::from_secs(15 * 60);
+const MAX_ACCOUNT_FAILURES: u32 = 5;
+const ACCOUNT_WINDOW: Duration = Duration::from_secs(15 * 60);
+pub const ACCOUNT_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Counts failed attempts per key within a fixed window. Implementations may keep the
+/// counters in process or in a shared store when running several instances.
+#[rocket::async_trait]
+pub trait AttemptStore: Send + Sync {
+    /// Records a failure and returns the number of failures in the current window.
+    async fn record_failure(&self, key: &str, window: Duration) -> u32;
+
+    /// Time left in the current window when it already holds at least `limit` failures.
+    async fn retry_after(&self, key: &str, limit: u32) -> Option<Duration>;
+
+    async fn reset(&self, key: &str);
+}
+
+struct Window {
+    failures: u32,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+pub struct InMemoryAttemptStore {
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+#[rocket::async_trait]
+impl AttemptStore for InMemoryAttemptStore {
+    async fn record_failure(&self, key: &str, window: Duration) -> u32 {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        windows.retain(|_, w| w.expires_at > now);
+
+        let entry = windows.entry(key.to_string()).or_insert(Window {
+            failures: 0,
+            expires_at: now + window,
+        });
+        entry.failures += 1;
+        entry.failures
+    }
+
+    async fn retry_after(&self, key: &str, limit: u32) -> Option<Duration> {
+        let now = Instant::now();
+        let windows = self.windows.lock().unwrap();
+
+        windows
+            .get(key)
+            .filter(|w| w.expires_at > now && w.failures >= limit)
+            .map(|w| w.expires_at - now)
+    }
+
+    async fn reset(&self, key: &str) {
+        self.windows.lock().unwrap().remove(key);
+    }
+}
+
+/// Throttles failed logins per client IP and per email. Emails without an account are
+/// locked just like the others, so the responses do not tell which ones exist.
+pub struct LoginRateLimiter {
+    store: Box<dyn AttemptStore>,
+}
+
+impl LoginRateLimiter {
+    pub fn new(store: Box<dyn AttemptStore>) -> Self {
+        Self { store }
+    }
+
+    fn ip_key(ip: &str) -> String {
+        format!("login:ip:{}", ip)
+    }
+
+    fn account_key(email: &str) -> String {
+        format!("login:account:{}", normalize_email(email))
+    }
+
+    fn lock_key(email: &str) -> String {
+        format!("login:lock:{}", normalize_email(email))
+    }
+
+    pub async fn ip_retry_after(&self, ip: &str) -> Option<Duration> {
+        self.store
+            .retry_after(&Self::ip_key(ip), MAX_IP_FAILURES)
+            .await
+    }
+
+    /// Time left until the email is unlocked, if it is locked.
+    pub async fn account_retry_after(&self, email: &str) -> Option<Duration> {
+        self.store.retry_after(&Self::lock_key(email), 1).await
+    }
+
+    /// Records a failed login and returns `true` when it locked the email for
+    /// `ACCOUNT_LOCKOUT`.
+    pub async fn record_failure(&self, ip: Option<&str>, email: &str) -> bool {
+        if let Some(ip) = ip {
+            self.store
+                .record_failure(&Self::ip_key(ip), IP_WINDOW)
+                .await;
+        }
+
+        let key = Self::account_key(email);
+        let failures = self.store.record_failure(&key, ACCOUNT_WINDOW).await;
+        if failures >= MAX_ACCOUNT_FAILURES {
+            self.store.reset(&key).await;
+            self.store
+                .record_failure(&Self::lock_key(email), ACCOUNT_LOCKOUT)
+                .await;
+            return true;
+        }
+        false
+    }
+
+    pub async fn record_success(&self, email: &str) {
+        self.store.reset(&Self::account_key(email)).await;
+    }
+
+    pub async fn unlock(&self, email: &str) {
+        self.store.reset(&Self::lock_key(email)).await;
+    }
+}
+
+impl Default for LoginRateLimiter {
+    fn default() -> Self {
+        Self::new(Box::new(InMemoryAttemptStore::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rocket::async_test]
+    async fn test_account_is_locked_after_repeated_failures() {
+        let limiter = LoginRateLimiter::default();
+
+        for _ in 1..MAX_ACCOUNT_FAILURES {
+            assert!(!limiter.record_failure(None, "Seller@example.com").await);
+        }
+        assert!(limiter
+            .account_retry_after("Seller@example.com")
+            .await
+            .is_none());
+        assert!(limiter.record_failure(None, "seller@example.com").await);
+
+        let retry_after = limiter.account_retry_after("Seller@example.com").await;
+        assert!(retry_after.is_some_and(|retry_after| retry_after <= ACCOUNT_LOCKOUT));
+        assert!(limiter
+            .account_retry_after("buyer@example.com")
+            .await
+            .is_none());
+
+        // The counter starts over once the lock has been handed out.
+        assert!(!limiter.record_failure(None, "seller@example.com").await);
+
+        limiter.unlock("seller@example.com").await;
+        assert!(limiter
+            .account_retry_after("seller@example.com")
+            .await
+            .is_none());
+    }
+
+    #[rocket::async_test]
+    async fn test_success_resets_account_failures() {
+        let limiter = LoginRateLimiter::default();
+
+        for _ in 1..MAX_ACCOUNT_FAILURES {
+            limiter.record_failure(None, "seller@example.com").await;
+        }
+        limiter.record_success("seller@example.com").await;
+
+        assert!(!limiter.record_failure(None, "seller@example.com").await);
+    }
+
+    #[rocket::async_test]
+    async fn test_ip_is_throttled() {
+        let limiter = LoginRateLimiter::default();
+
+        for i in 0..MAX_IP_FAILURES {
+            assert!(limiter.ip_retry_after("10.0.0.1").await.is_none());
+            limiter
+                .record_failure(Some("10.0.0.1"), &format!("user{}@example.com", i))
+                .await;
+        }
+
+        let retry_after = limiter.ip_retry_after("10.0.0.1").await.unwrap();
+        assert!(retry_after <= IP_WINDOW);
+        assert!(limiter.ip_retry_after("10.0.0.2").await.is_none());
+    }
+}
@@ -1,134 +1,152 @@
 use aws_config::{BehaviorVersion, Region};
 use aws_sdk_s3::config::Credentials;
+use aws_sdk_s3::presigning::PresigningConfig;
 use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::types::ObjectCannedAcl;
 use aws_sdk_s3::{Client as S3Client, Config};
-use std::path::Path;
 
-pub struct S3Storage {
-    client: S3Client,
-    bucket: String,
-    region: String,
+use chrono::DateTime;
+use std::time::Duration;
+
+use crate::utils::storage::{PresignedUpload, StorageBackend, StoredObject};
+
+/// Settings read from the `AWS_*` variables.
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Config {
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// `AWS_ENDPOINT_URL`: an S3-compatible service like MinIO instead of AWS.
+    pub endpoint_url: Option<String>,
+    /// `AWS_FORCE_PATH_STYLE`: address the bucket as `<endpoint>/<bucket>` rather than
+    /// as a subdomain, which most self-hosted services need.
+    pub force_path_style: bool,
+    /// `AWS_PUBLIC_URL`: prefix of the URLs given out for objects, e.g. a CDN.
+    pub public_url: Option<String>,
+    /// `AWS_OBJECT_ACL`: canned ACL set on uploads, `public-read` unless it is `none`.
+    pub acl: Option<ObjectCannedAcl>,
 }
 
-#[derive(Debug)]
-pub struct UploadedImage {
-    pub url: String,
-    pub key: String,
+impl S3Config {
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let required = |name: &str| var(name).ok_or_else(|| format!("{} not set", name));
+        let optional = |name: &str| var(name).filter(|value| !value.is_empty());
+
+        let acl = match optional("AWS_OBJECT_ACL").as_deref() {
+            None => Some(ObjectCannedAcl::PublicRead),
+            Some("none") => None,
+            Some(acl) => Some(
+                ObjectCannedAcl::try_parse(acl)
+                    .map_err(|_| format!("Unknown AWS_OBJECT_ACL: {}", acl))?,
+            ),
+        };
+
+        Ok(Self {
+            bucket: required("AWS_BUCKET_NAME")?,
+            region: required("AWS_BUCKET_REGION")?,
+            access_key: required("AWS_ACCESS_KEY_ID")?,
+            secret_key: required("AWS_SECRET_ACCESS_KEY")?,
+            endpoint_url: optional("AWS_ENDPOINT_URL"),
+            force_path_style: matches!(
+                optional("AWS_FORCE_PATH_STYLE").as_deref(),
+                Some("true") | Some("1")
+            ),
+            public_url: optional("AWS_PUBLIC_URL"),
+            acl,
+        })
+    }
+
+    /// Where the objects are publicly reachable, without a trailing slash.
+    fn public_base_url(&self) -> String {
+        if let Some(public_url) = &self.public_url {
+            return public_url.trim_end_matches('/').to_string();
+        }
+
+        match &self.endpoint_url {
+            Some(endpoint) => {
+                let endpoint = endpoint.trim_end_matches('/');
+                match endpoint.split_once("://") {
+                    Some((scheme, host)) if !self.force_path_style => {
+                        format!("{}://{}.{}", scheme, self.bucket, host)
+                    }
+                    _ => format!("{}/{}", endpoint, self.bucket),
+                }
+            }
+            None if self.force_path_style => {
+                format!("https://s3.{}.amazonaws.com/{}", self.region, self.bucket)
+            }
+            None => format!("https://{}.s3.{}.amazonaws.com", self.bucket, self.region),
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct UploadResult {
-    pub successful: Vec<UploadedImage>,
-    pub failed: Vec<(String, String)>, // (filename, error message)
+#[derive(Clone)]
+pub struct S3Storage {
+    client: S3Client,
+    bucket: String,
+    public_base_url: String,
+    acl: Option<ObjectCannedAcl>,
 }
 
 impl S3Storage {
     pub async fn new() -> Result<Self, String> {
-        let bucket = std::env::var("AWS_BUCKET_NAME")
-            .map_err(|e| format!("AWS_BUCKET_NAME not set: {}", e))?;
-        let region = std::env::var("AWS_BUCKET_REGION")
-            .map_err(|e| format!("AWS_BUCKET_REGION not set: {}", e))?;
-        let access_key = std::env::var("AWS_ACCESS_KEY_ID")
-            .map_err(|e| format!("AWS_ACCESS_KEY_ID not set: {}", e))?;
-        let secret_key = std::env::var("AWS_SECRET_ACCESS_KEY")
-            .map_err(|e| format!("AWS_SECRET_ACCESS_KEY not set: {}", e))?;
-
-        let shared_config = Config::builder()
+        Ok(Self::from_config(S3Config::from_env()?))
+    }
+
+    pub fn from_config(config: S3Config) -> Self {
+        let mut builder = Config::builder()
             .behavior_version(BehaviorVersion::latest())
-            .region(Region::new(region.clone()))
+            .region(Region::new(config.region.clone()))
             .credentials_provider(Credentials::new(
-                access_key, secret_key, None, None, "example",
+                config.access_key.clone(),
+                config.secret_key.clone(),
+                None,
+                None,
+                "example",
             ))
-            .build();
+            .force_path_style(config.force_path_style);
+        if let Some(endpoint_url) = &config.endpoint_url {
+            builder = builder.endpoint_url(endpoint_url);
+        }
 
-        let client = S3Client::from_conf(shared_config);
+        let client = S3Client::from_conf(builder.build());
 
-        Ok(Self {
+        Self {
             client,
-            bucket,
-            region,
-        })
+            public_base_url: config.public_base_url(),
+            bucket: config.bucket,
+            acl: config.acl,
+        }
     }
+}
 
-    pub async fn upload_image(
-        &self,
-        raw_data: &[u8],
-        filename: &str,
-    ) -> Result<UploadedImage, String> {
-        println!("Uploading image to S3: {}", filename);
-
-        let byte_stream = ByteStream::from(raw_data.to_vec());
-        let extension = Path::new(filename)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("jpg");
-
-        let key = format!("uploads/{}.{}", uuid::Uuid::new_v4(), extension);
+#[rocket::async_trait]
+impl StorageBackend for S3Storage {
+    async fn upload(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<(), String> {
+        rocket::debug!("Uploading image to S3: {}", key);
 
         match self
             .client
             .put_object()
             .bucket(&self.bucket)
-            .key(&key)
-            .body(byte_stream)
-            .content_type(format!("image/{}", extension))
-            .acl(ObjectCannedAcl::PublicRead)
+            .key(key)
+            .body(ByteStream::from(data))
+            .content_type(content_type)
+            .set_acl(self.acl.clone())
             .send()
             .await
         {
-            Ok(_) => {
-                let url = format!(
-                    "https://{}.s3.{}.amazonaws.com/{}",
-                    self.bucket, self.region, key
-                );
-                Ok(UploadedImage { url, key })
-            }
+            Ok(_) => Ok(()),
             Err(e) => Err(e.to_string()),
         }
     }
 
-    pub async fn upload_multiple_images(&self, images: Vec<(&[u8], String)>) -> UploadResult {
-        let mut handles: Vec<
-            tokio::task::JoinHandle<
-                Result<(String, Result<UploadedImage, String>), tokio::task::JoinError>,
-            >,
-        > = Vec::with_capacity(images.len());
-
-        // Start all uploads concurrently
-        for (data, filename) in images {
-            let data = data.to_vec(); // Clone the data for each task
-            let filename = filename.clone();
-            handles.push(tokio::spawn({
-                let self_clone = self.clone();
-                async move {
-                    match self_clone.upload_image(&data, &filename).await {
-                        Ok(uploaded) => Ok((filename, Ok(uploaded))),
-                        Err(e) => Ok((filename, Err(e))),
-                    }
-                }
-            }));
-        }
-
-        let mut successful = Vec::new();
-        let mut failed = Vec::new();
-
-        // Collect results
-        for handle in handles {
-            match handle.await {
-                Ok(Ok((filename, result))) => match result {
-                    Ok(uploaded) => successful.push(uploaded),
-                    Err(error) => failed.push((filename, error)),
-                },
-                Ok(Err(e)) => failed.push(("unknown".to_string(), e.to_string())),
-                Err(e) => failed.push(("unknown".to_string(), format!("Task failed: {}", e))),
-            }
-        }
-
-        UploadResult { successful, failed }
-    }
-
-    pub async fn delete_image(&self, key: &str) -> Result<(), String> {
+    async fn delete(&self, key: &str) -> Result<(), String> {
         match self
             .client
             .delete_object()
@@ -142,34 +160,196 @@ impl S3Storage {
         }
     }
 
-    pub async fn delete_multiple_images(&self, keys: Vec<String>) -> Result<(), String> {
-        let mut handles = Vec::with_capacity(keys.len());
+    fn url_for(&self, key: &str) -> String {
+        format!("{}/{}", self.public_base_url, key)
+    }
 
-        for key in keys {
-            handles.push(tokio::spawn({
-                let self_clone = self.clone();
-                async move { self_clone.delete_image(&key).await }
-            }));
+    async fn size(&self, key: &str) -> Result<Option<u64>, String> {
+        match self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(object) => Ok(Some(
+                object.content_length().unwrap_or_default().max(0) as u64
+            )),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(None),
+            Err(e) => Err(format!("Failed to check image: {}", e)),
         }
+    }
 
-        for handle in handles {
-            match handle.await {
-                Ok(result) => result?,
-                Err(e) => return Err(format!("Task failed: {}", e)),
+    async fn download(&self, key: &str) -> Result<Vec<u8>, String> {
+        let object = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to download image: {}", e))?;
+
+        object
+            .body
+            .collect()
+            .await
+            .map(|data| data.into_bytes().to_vec())
+            .map_err(|e| format!("Failed to download image: {}", e))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, String> {
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(prefix)
+            .into_paginator()
+            .send();
+
+        let mut objects = Vec::new();
+        while let Some(page) = pages.next().await {
+            let page = page.map_err(|e| format!("Failed to list images: {}", e))?;
+            for object in page.contents() {
+                if let Some(key) = object.key() {
+                    objects.push(StoredObject {
+                        key: key.to_string(),
+                        last_modified: object
+                            .last_modified()
+                            .and_then(|time| DateTime::from_timestamp(time.secs(), 0)),
+                    });
+                }
             }
         }
 
-        Ok(())
+        Ok(objects)
+    }
+
+    async fn presign_upload(
+        &self,
+        key: &str,
+        content_type: &str,
+        expires_in: Duration,
+    ) -> Result<Option<PresignedUpload>, String> {
+        let config = PresigningConfig::expires_in(expires_in).map_err(|e| e.to_string())?;
+
+        let request = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .set_acl(self.acl.clone())
+            .presigned(config)
+            .await
+            .map_err(|e| format!("Failed to presign upload: {}", e))?;
+
+        Ok(Some(PresignedUpload {
+            url: request.uri().to_string(),
+            headers: request
+                .headers()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }))
     }
 }
 
-// Implement Clone for S3Storage
-impl Clone for S3Storage {
-    fn clone(&self) -> Self {
-        Self {
-            client: self.client.clone(),
-            bucket: self.bucket.clone(),
-            region: self.region.clone(),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(vars: &[(&str, &str)]) -> Result<S3Config, String> {
+        let defaults = [
+            ("AWS_BUCKET_NAME", "photos"),
+            ("AWS_BUCKET_REGION", "eu-central-1"),
+            ("AWS_ACCESS_KEY_ID", "key"),
+            ("AWS_SECRET_ACCESS_KEY", "secret"),
+        ];
+        S3Config::from_vars(|name| {
+            vars.iter()
+                .chain(defaults.iter())
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_defaults_match_aws() {
+        let config = config(&[]).unwrap();
+
+        assert_eq!(config.acl, Some(ObjectCannedAcl::PublicRead));
+        assert!(!config.force_path_style);
+        assert_eq!(
+            config.public_base_url(),
+            "https://photos.s3.eu-central-1.amazonaws.com"
+        );
+    }
+
+    #[test]
+    fn test_custom_endpoint() {
+        let path_style = config(&[
+            ("AWS_ENDPOINT_URL", "http://localhost:9000/"),
+            ("AWS_FORCE_PATH_STYLE", "true"),
+        ])
+        .unwrap();
+        assert_eq!(path_style.public_base_url(), "http://localhost:9000/photos");
+
+        let virtual_host = config(&[("AWS_ENDPOINT_URL", "https://storage.example.com")]).unwrap();
+        assert_eq!(
+            virtual_host.public_base_url(),
+            "https://photos.storage.example.com"
+        );
+    }
+
+    #[test]
+    fn test_public_url_wins() {
+        let config = config(&[
+            ("AWS_ENDPOINT_URL", "http://localhost:9000"),
+            ("AWS_PUBLIC_URL", "https://cdn.example.com/"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.public_base_url(), "https://cdn.example.com");
+    }
+
+    #[test]
+    fn test_acl() {
+        assert_eq!(config(&[("AWS_OBJECT_ACL", "none")]).unwrap().acl, None);
+        assert_eq!(
+            config(&[("AWS_OBJECT_ACL", "private")]).unwrap().acl,
+            Some(ObjectCannedAcl::Private)
+        );
+        assert!(config(&[("AWS_OBJECT_ACL", "everyone")]).is_err());
+    }
+
+    #[rocket::async_test]
+    async fn test_presign_upload() {
+        let storage = S3Storage::from_config(
+            config(&[
+                ("AWS_ENDPOINT_URL", "http://localhost:9000"),
+                ("AWS_FORCE_PATH_STYLE", "true"),
+            ])
+            .unwrap(),
+        );
+
+        let upload = storage
+            .presign_upload("uploads/a.jpg", "image/jpeg", Duration::from_secs(60))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(upload
+            .url
+            .starts_with("http://localhost:9000/photos/uploads/a.jpg?"));
+        assert!(upload.url.contains("X-Amz-Signature="));
+        assert_eq!(upload.headers["content-type"], "image/jpeg");
+        assert_eq!(upload.headers["x-amz-acl"], "public-read");
+    }
+
+    #[test]
+    fn test_missing_credentials() {
+        let result = S3Config::from_vars(|_| None);
+        assert_eq!(result, Err("AWS_BUCKET_NAME not set".to_string()));
     }
 }
@@ -0,0 +1,24 @@
+use data_encoding::{BASE64URL_NOPAD, HEXLOWER};
+use rand::RngCore;
+use ring::digest;
+
+const TOKEN_LEN: usize = 32;
+
+/// Random single-use tokens that are mailed to users. Only the SHA-256 hash is stored,
+/// so a leaked database does not leak usable tokens.
+pub struct SecureToken;
+
+impl SecureToken {
+    /// Returns the token to hand out together with the hash to store.
+    pub fn generate() -> (String, String) {
+        let mut bytes = [0u8; TOKEN_LEN];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = BASE64URL_NOPAD.encode(&bytes);
+        let hash = Self::hash(&token);
+        (token, hash)
+    }
+
+    pub fn hash(token: &str) -> String {
+        HEXLOWER.encode(digest::digest(&digest::SHA256, token.as_bytes()).as_ref())
+    }
+}
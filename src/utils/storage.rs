@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::utils::s3_storage::S3Storage;
+
+/// Where the local backend's files are served from, see `local_storage_dir`.
+pub const LOCAL_FILES_PATH: &str = "/files";
+
+/// An object store for uploaded images. Keys are relative paths like `uploads/<uuid>.jpg`.
+#[rocket::async_trait]
+pub trait StorageBackend: Send + Sync {
+    async fn upload(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<(), String>;
+
+    /// Deleting a key that does not exist succeeds.
+    async fn delete(&self, key: &str) -> Result<(), String>;
+
+    /// The public URL the object is served at.
+    fn url_for(&self, key: &str) -> String;
+
+    /// The size of the object in bytes, `None` if there is none under `key`.
+    async fn size(&self, key: &str) -> Result<Option<u64>, String>;
+
+    async fn exists(&self, key: &str) -> Result<bool, String> {
+        self.size(key).await.map(|size| size.is_some())
+    }
+
+    async fn download(&self, key: &str) -> Result<Vec<u8>, String>;
+
+    /// Every object whose key starts with `prefix`, in no particular order.
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, String>;
+
+    /// A URL clients can upload the object to themselves, if the backend supports it.
+    async fn presign_upload(
+        &self,
+        _key: &str,
+        _content_type: &str,
+        _expires_in: Duration,
+    ) -> Result<Option<PresignedUpload>, String> {
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub key: String,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// A `PUT` request the client sends directly to the storage, with exactly these headers.
+#[derive(Debug, Serialize)]
+pub struct PresignedUpload {
+    pub url: String,
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug)]
+pub struct UploadedImage {
+    pub url: String,
+    pub key: String,
+}
+
+/// Stores objects as files under `root`, served by a `FileServer` at `LOCAL_FILES_PATH`.
+pub struct LocalStorage {
+    root: PathBuf,
+    base_url: String,
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf, base_url: String) -> Self {
+        Self { root, base_url }
+    }
+
+    /// Refuses keys that would escape `root`.
+    fn path_for(&self, key: &str) -> Result<PathBuf, String> {
+        let relative = Path::new(key);
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(format!("Invalid storage key: {}", key));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+#[rocket::async_trait]
+impl StorageBackend for LocalStorage {
+    async fn upload(&self, key: &str, data: Vec<u8>, _content_type: &str) -> Result<(), String> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+        }
+
+        tokio::fs::write(&path, data)
+            .await
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        let path = self.path_for(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!("Cannot delete {}: {}", path.display(), e)),
+        }
+    }
+
+    fn url_for(&self, key: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), key)
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<u64>, String> {
+        let path = self.path_for(key)?;
+        match tokio::fs::metadata(&path).await {
+            Ok(metadata) => Ok(Some(metadata.len())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Cannot check {}: {}", path.display(), e)),
+        }
+    }
+
+    async fn download(&self, key: &str) -> Result<Vec<u8>, String> {
+        let path = self.path_for(key)?;
+        tokio::fs::read(&path)
+            .await
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, String> {
+        let root = self.root.clone();
+        let prefix = prefix.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let mut objects = Vec::new();
+            let mut dirs = vec![root.clone()];
+
+            while let Some(dir) = dirs.pop() {
+                let entries = match std::fs::read_dir(&dir) {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(format!("Cannot list {}: {}", dir.display(), e)),
+                };
+
+                for entry in entries {
+                    let entry =
+                        entry.map_err(|e| format!("Cannot list {}: {}", dir.display(), e))?;
+                    let metadata = entry
+                        .metadata()
+                        .map_err(|e| format!("Cannot read {}: {}", entry.path().display(), e))?;
+                    if metadata.is_dir() {
+                        dirs.push(entry.path());
+                        continue;
+                    }
+
+                    let Some(key) = entry
+                        .path()
+                        .strip_prefix(&root)
+                        .ok()
+                        .and_then(|path| path.to_str())
+                        .map(|key| key.replace(std::path::MAIN_SEPARATOR, "/"))
+                    else {
+                        continue;
+                    };
+                    if key.starts_with(&prefix) {
+                        objects.push(StoredObject {
+                            key,
+                            last_modified: metadata.modified().ok().map(DateTime::from),
+                        });
+                    }
+                }
+            }
+
+            Ok(objects)
+        })
+        .await
+        .map_err(|e| format!("Task failed: {}", e))?
+    }
+}
+
+/// Keeps objects in memory so tests can run without any storage.
+#[derive(Default)]
+pub struct MemoryStorage {
+    objects: Mutex<HashMap<String, MemoryObject>>,
+}
+
+struct MemoryObject {
+    data: Vec<u8>,
+    uploaded_at: DateTime<Utc>,
+}
+
+impl MemoryStorage {
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.objects
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|object| object.data.clone())
+    }
+}
+
+#[rocket::async_trait]
+impl StorageBackend for MemoryStorage {
+    async fn upload(&self, key: &str, data: Vec<u8>, _content_type: &str) -> Result<(), String> {
+        self.objects.lock().unwrap().insert(
+            key.to_string(),
+            MemoryObject {
+                data,
+                uploaded_at: Utc::now(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), String> {
+        self.objects.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    fn url_for(&self, key: &str) -> String {
+        format!("memory://{}", key)
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<u64>, String> {
+        Ok(self
+            .objects
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|object| object.data.len() as u64))
+    }
+
+    async fn download(&self, key: &str) -> Result<Vec<u8>, String> {
+        self.get(key).ok_or_else(|| format!("No object {}", key))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<StoredObject>, String> {
+        Ok(self
+            .objects
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(key, _)| key.starts_with(prefix))
+            .map(|(key, object)| StoredObject {
+                key: key.clone(),
+                last_modified: Some(object.uploaded_at),
+            })
+            .collect())
+    }
+}
+
+/// The directory of the local backend when `STORAGE_BACKEND` selects it.
+pub fn local_storage_dir() -> Option<PathBuf> {
+    match std::env::var("STORAGE_BACKEND").as_deref() {
+        Ok("local") => Some(
+            std::env::var("LOCAL_STORAGE_DIR")
+                .unwrap_or_else(|_| "storage".to_string())
+                .into(),
+        ),
+        _ => None,
+    }
+}
+
+/// Picks the backend from `STORAGE_BACKEND`: `s3` (default) with the `AWS_*` variables,
+/// `local` with `LOCAL_STORAGE_DIR` and `LOCAL_STORAGE_URL`, or `memory`.
+pub async fn storage_from_env() -> Result<Arc<dyn StorageBackend>, String> {
+    match std::env::var("STORAGE_BACKEND").as_deref() {
+        Ok("s3") | Err(_) => Ok(Arc::new(S3Storage::new().await?)),
+        Ok("local") => {
+            let base_url =
+                std::env::var("LOCAL_STORAGE_URL").unwrap_or_else(|_| LOCAL_FILES_PATH.to_string());
+            let root = local_storage_dir().unwrap_or_default();
+            Ok(Arc::new(LocalStorage::new(root, base_url)))
+        }
+        Ok("memory") => Ok(Arc::new(MemoryStorage::default())),
+        Ok(other) => Err(format!("Unknown STORAGE_BACKEND: {}", other)),
+    }
+}
+
+/// A fresh key under `uploads/`, keeping the extension of `filename` if it is one of an
+/// image format, `jpg` otherwise.
+pub fn new_upload_key(filename: &str) -> String {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| image::ImageFormat::from_extension(ext).is_some())
+        .map_or("jpg".to_string(), |ext| ext.to_ascii_lowercase());
+
+    format!("uploads/{}.{}", uuid::Uuid::new_v4(), extension)
+}
+
+/// Whether `key` has the shape `new_upload_key` gives out, `uploads/<uuid>.<extension>`.
+pub fn is_upload_key(key: &str) -> bool {
+    key.strip_prefix("uploads/")
+        .and_then(|name| name.split_once('.'))
+        .is_some_and(|(id, extension)| {
+            uuid::Uuid::try_parse(id).is_ok()
+                && !extension.is_empty()
+                && extension.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+/// Uploads a new image under a fresh key.
+pub async fn upload_image(
+    storage: &dyn StorageBackend,
+    raw_data: &[u8],
+    filename: &str,
+) -> Result<UploadedImage, String> {
+    let key = new_upload_key(filename);
+    let extension = key.rsplit('.').next().unwrap_or("jpg");
+
+    storage
+        .upload(&key, raw_data.to_vec(), &format!("image/{}", extension))
+        .await?;
+
+    Ok(UploadedImage {
+        url: storage.url_for(&key),
+        key,
+    })
+}
+
+/// Uploads `(data, key, content_type)` objects concurrently. The results are in the same
+/// order as `objects`.
+pub async fn upload_objects(
+    storage: &Arc<dyn StorageBackend>,
+    objects: Vec<(Vec<u8>, String, &'static str)>,
+) -> Vec<Result<UploadedImage, String>> {
+    let mut handles = Vec::with_capacity(objects.len());
+
+    // Start all uploads concurrently
+    for (data, key, content_type) in objects {
+        let storage = storage.clone();
+        handles.push(tokio::spawn(async move {
+            storage.upload(&key, data, content_type).await?;
+            Ok(UploadedImage {
+                url: storage.url_for(&key),
+                key,
+            })
+        }));
+    }
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(match handle.await {
+            Ok(result) => result,
+            Err(e) => Err(format!("Task failed: {}", e)),
+        });
+    }
+
+    results
+}
+
+/// Deletes the keys concurrently, failing if any of them fails.
+pub async fn delete_objects(
+    storage: &Arc<dyn StorageBackend>,
+    keys: Vec<String>,
+) -> Result<(), String> {
+    let mut handles = Vec::with_capacity(keys.len());
+
+    for key in keys {
+        let storage = storage.clone();
+        handles.push(tokio::spawn(async move { storage.delete(&key).await }));
+    }
+
+    for handle in handles {
+        match handle.await {
+            Ok(result) => result?,
+            Err(e) => return Err(format!("Task failed: {}", e)),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rocket::async_test]
+    async fn test_memory_storage() {
+        let storage = MemoryStorage::default();
+
+        storage
+            .upload("uploads/a.jpg", vec![1, 2, 3], "image/jpeg")
+            .await
+            .unwrap();
+        assert!(storage.exists("uploads/a.jpg").await.unwrap());
+        assert_eq!(storage.size("uploads/a.jpg").await.unwrap(), Some(3));
+        assert_eq!(storage.get("uploads/a.jpg"), Some(vec![1, 2, 3]));
+
+        storage.delete("uploads/a.jpg").await.unwrap();
+        assert!(!storage.exists("uploads/a.jpg").await.unwrap());
+        storage.delete("uploads/a.jpg").await.unwrap();
+        assert!(storage
+            .presign_upload("uploads/a.jpg", "image/jpeg", Duration::from_secs(60))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_upload_keys() {
+        let key = new_upload_key("photo.png");
+        assert!(key.ends_with(".png"));
+        assert!(is_upload_key(&key));
+        assert!(new_upload_key("PHOTO.JPEG").ends_with(".jpeg"));
+        for filename in ["photo", "photo.exe", "photo.p-n-g", "photo.pn g"] {
+            let key = new_upload_key(filename);
+            assert!(key.ends_with(".jpg"), "{}", key);
+            assert!(is_upload_key(&key), "{}", key);
+        }
+
+        assert!(!is_upload_key("uploads/photo.png"));
+        assert!(!is_upload_key(
+            "other/0b8e4a52-6f0e-4b8e-9a43-3c1f5c1c2b7d.png"
+        ));
+        assert!(!is_upload_key(
+            "uploads/0b8e4a52-6f0e-4b8e-9a43-3c1f5c1c2b7d.png/../x"
+        ));
+        assert!(!is_upload_key(
+            "uploads/0b8e4a52-6f0e-4b8e-9a43-3c1f5c1c2b7d-thumbnail.jpg"
+        ));
+    }
+
+    #[rocket::async_test]
+    async fn test_local_storage() {
+        let root = std::env::temp_dir().join(format!("storage-{}", uuid::Uuid::new_v4()));
+        let storage = LocalStorage::new(root.clone(), "/files/".to_string());
+
+        storage
+            .upload("uploads/a.jpg", vec![1, 2, 3], "image/jpeg")
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read(root.join("uploads/a.jpg")).unwrap(),
+            vec![1, 2, 3]
+        );
+        assert!(storage.exists("uploads/a.jpg").await.unwrap());
+        assert_eq!(storage.size("uploads/a.jpg").await.unwrap(), Some(3));
+        assert_eq!(storage.url_for("uploads/a.jpg"), "/files/uploads/a.jpg");
+
+        storage
+            .upload("other/b.jpg", vec![4], "image/jpeg")
+            .await
+            .unwrap();
+        let listed = storage.list("uploads/").await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].key, "uploads/a.jpg");
+        assert!(listed[0].last_modified.is_some());
+
+        storage.delete("uploads/a.jpg").await.unwrap();
+        assert!(!storage.exists("uploads/a.jpg").await.unwrap());
+        assert!(storage.list("uploads/").await.unwrap().is_empty());
+
+        assert!(storage
+            .upload("../a.jpg", vec![], "image/jpeg")
+            .await
+            .is_err());
+        assert!(storage.exists("/etc/passwd").await.is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[rocket::async_test]
+    async fn test_upload_objects_keeps_order() {
+        let storage: Arc<dyn StorageBackend> = Arc::new(MemoryStorage::default());
+        let objects = (0..5)
+            .map(|i| (vec![i], format!("uploads/{}.jpg", i), "image/jpeg"))
+            .collect();
+
+        let results = upload_objects(&storage, objects).await;
+
+        let keys: Vec<_> = results.into_iter().map(|r| r.unwrap().key).collect();
+        assert_eq!(
+            keys,
+            (0..5)
+                .map(|i| format!("uploads/{}.jpg", i))
+                .collect::<Vec<_>>()
+        );
+
+        delete_objects(&storage, keys).await.unwrap();
+        assert!(!storage.exists("uploads/0.jpg").await.unwrap());
+    }
+}
@@ -0,0 +1,143 @@
+use serde::Serialize;
+
+use crate::errors::ApiError;
+
+/// A constraint on a single form or JSON field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// Text must contain something besides whitespace.
+    NotEmpty,
+    /// Text may have at most this many characters, matching the column's `VARCHAR` size.
+    MaxLength(usize),
+    /// Text must look like an email address.
+    Email,
+    /// Number must lie within the inclusive range.
+    Range(i32, i32),
+}
+
+impl Rule {
+    fn check_text(&self, value: &str) -> Option<String> {
+        match *self {
+            Rule::NotEmpty if value.trim().is_empty() => Some("must not be empty".to_string()),
+            Rule::MaxLength(max) if value.chars().count() > max => {
+                Some(format!("must be at most {} characters long", max))
+            }
+            Rule::Email if !is_email(value) => Some("must be a valid email address".to_string()),
+            _ => None,
+        }
+    }
+
+    fn check_number(&self, value: i32) -> Option<String> {
+        match *self {
+            Rule::Range(min, max) if !(min..=max).contains(&value) => {
+                Some(format!("must be between {} and {}", min, max))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn is_email(value: &str) -> bool {
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').count() > 1
+                && domain.split('.').all(|part| !part.is_empty())
+                && !value.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Collects field errors so a request can report all of them at once.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn error(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Records the first rule `value` breaks, if any.
+    pub fn text(&mut self, field: &str, value: &str, rules: &[Rule]) {
+        if let Some(message) = rules.iter().find_map(|rule| rule.check_text(value)) {
+            self.error(field, message);
+        }
+    }
+
+    /// Records the first rule `value` breaks, if any.
+    pub fn number(&mut self, field: &str, value: i32, rules: &[Rule]) {
+        if let Some(message) = rules.iter().find_map(|rule| rule.check_number(value)) {
+            self.error(field, message);
+        }
+    }
+
+    pub fn finish(self) -> Result<(), ApiError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::InvalidFields(self.errors))
+        }
+    }
+}
+
+/// Implemented by JSON bodies that are checked before they reach the database.
+pub trait Validate {
+    fn validate(&self) -> Result<(), ApiError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collects_every_field_error() {
+        let mut validator = Validator::new();
+        validator.text("title", "  ", &[Rule::NotEmpty, Rule::MaxLength(5)]);
+        validator.text("name", "too long", &[Rule::NotEmpty, Rule::MaxLength(5)]);
+        validator.text("email", "someone@example", &[Rule::Email]);
+        validator.number("year", 1800, &[Rule::Range(2000, 2100)]);
+        validator.number("other_year", 2024, &[Rule::Range(2000, 2100)]);
+
+        match validator.finish() {
+            Err(ApiError::InvalidFields(errors)) => {
+                let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+                assert_eq!(fields, vec!["title", "name", "email", "year"]);
+                assert_eq!(errors[0].message, "must not be empty");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_max_length_counts_characters() {
+        let mut validator = Validator::new();
+        validator.text("title", "Živé Teplice", &[Rule::MaxLength(12)]);
+        assert!(validator.finish().is_ok());
+    }
+
+    #[test]
+    fn test_email_format() {
+        assert!(is_email("someone@example.com"));
+        assert!(!is_email("someone@"));
+        assert!(!is_email("someone@example."));
+        assert!(!is_email("some one@example.com"));
+        assert!(!is_email("example.com"));
+    }
+}
@@ -0,0 +1,257 @@
+use reqwest::{blocking::Client, StatusCode};
+use serde_json::{json, Value};
+
+pub mod common;
+
+#[test]
+fn test_get_users() {
+    let client = common::get_client_with_logged_in_admin();
+    let user = common::create_test_user(&Client::new());
+
+    let response = client
+        .get(format!(
+            "{}/admin/users?search={}&role=user&user_type=seller&request=notsend",
+            common::APP_HOST,
+            user["username"].as_str().unwrap()
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let page: Value = response.json().unwrap();
+    assert_eq!(
+        page,
+        json!({
+            "users": [user],
+            "page": 1,
+            "per_page": 20,
+            "total": 1
+        })
+    );
+
+    let response = client
+        .get(format!(
+            "{}/admin/users?search={}&role=admin",
+            common::APP_HOST,
+            user["username"].as_str().unwrap()
+        ))
+        .send()
+        .unwrap();
+
+    let page: Value = response.json().unwrap();
+    assert_eq!(page["total"], 0);
+}
+
+#[test]
+fn test_get_users_pagination() {
+    let client = common::get_client_with_logged_in_admin();
+    common::create_test_user(&Client::new());
+    common::create_test_user(&Client::new());
+
+    let response = client
+        .get(format!(
+            "{}/admin/users?page=2&per_page=1",
+            common::APP_HOST
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let page: Value = response.json().unwrap();
+    assert_eq!(page["users"].as_array().unwrap().len(), 1);
+    assert!(page["total"].as_i64().unwrap() >= 2);
+}
+
+#[test]
+fn test_get_users_forbidden_for_regular_user() {
+    let client = common::get_client_with_logged_in_user();
+
+    let response = client
+        .get(format!("{}/admin/users", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn test_update_user_role() {
+    let client = common::get_client_with_logged_in_admin();
+    let user = common::create_test_user(&Client::new());
+
+    let response = client
+        .put(format!(
+            "{}/admin/users/{}/role",
+            common::APP_HOST,
+            user["id"]
+        ))
+        .json(&json!({ "role": "admin" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let updated: Value = response.json().unwrap();
+    assert_eq!(updated["role"], "admin");
+}
+
+#[test]
+fn test_disable_user() {
+    let client = common::get_client_with_logged_in_admin();
+    let (user, user_client) = common::get_logged_in_test_user();
+
+    let response = client
+        .put(format!(
+            "{}/admin/users/{}/disable",
+            common::APP_HOST,
+            user["id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let disabled: Value = response.json().unwrap();
+    assert!(disabled["disabled_at"].is_string());
+
+    let response = user_client
+        .get(format!("{}/users/me", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    // Disabling an account revokes its sessions.
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = Client::new()
+        .post(format!("{}/auth/login", common::APP_HOST))
+        .json(&json!({
+            "email": user["email"],
+            "password": common::TEST_PASSWORD
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = client
+        .put(format!(
+            "{}/admin/users/{}/enable",
+            common::APP_HOST,
+            user["id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    common::login(&Client::new(), &user["email"], common::TEST_PASSWORD);
+}
+
+#[test]
+fn test_delete_user() {
+    let client = common::get_client_with_logged_in_admin();
+    let user = common::create_test_user(&Client::new());
+
+    let response = client
+        .delete(format!("{}/admin/users/{}", common::APP_HOST, user["id"]))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = Client::new()
+        .post(format!("{}/auth/login", common::APP_HOST))
+        .json(&json!({
+            "email": user["email"],
+            "password": common::TEST_PASSWORD
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn test_unlock_user() {
+    let client = common::get_client_with_logged_in_admin();
+    let user = common::create_test_user(&Client::new());
+
+    for _ in 0..5 {
+        Client::new()
+            .post(format!("{}/auth/login", common::APP_HOST))
+            .json(&json!({
+                "email": user["email"],
+                "password": "wrong_password"
+            }))
+            .send()
+            .unwrap();
+    }
+
+    let response = client
+        .get(format!(
+            "{}/admin/users?search={}",
+            common::APP_HOST,
+            user["username"].as_str().unwrap()
+        ))
+        .send()
+        .unwrap();
+
+    let page: Value = response.json().unwrap();
+    assert!(page["users"][0]["locked_until"].is_string());
+
+    let response = client
+        .put(format!(
+            "{}/admin/users/{}/unlock",
+            common::APP_HOST,
+            user["id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let unlocked: Value = response.json().unwrap();
+    assert!(unlocked["locked_until"].is_null());
+
+    common::login(&Client::new(), &user["email"], common::TEST_PASSWORD);
+}
+
+#[test]
+fn test_revoke_user_sessions() {
+    let client = common::get_client_with_logged_in_admin();
+    let (user, user_client) = common::get_logged_in_test_user();
+
+    let response = client
+        .get(format!(
+            "{}/admin/users/{}/sessions",
+            common::APP_HOST,
+            user["id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let sessions: Value = response.json().unwrap();
+    assert_eq!(sessions.as_array().unwrap().len(), 1);
+
+    let response = client
+        .delete(format!(
+            "{}/admin/users/{}/sessions",
+            common::APP_HOST,
+            user["id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = user_client
+        .get(format!("{}/users/me", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
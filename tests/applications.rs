@@ -0,0 +1,144 @@
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde_json::Value;
+
+pub mod common;
+
+#[test]
+fn test_submit_application() {
+    let admin_client = common::get_client_with_logged_in_admin();
+    common::create_current_test_event(&admin_client);
+    let (user, client) = common::get_logged_in_test_user();
+
+    let application = common::submit_test_application(&client);
+
+    assert_eq!(application["id"], user["id"]);
+    assert_eq!(application["request"], "pending");
+    assert!(application["event_id"].is_i64());
+
+    let response = client
+        .post(format!("{}/applications", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[test]
+fn test_get_pending_applications() {
+    let admin_client = common::get_client_with_logged_in_admin();
+    common::create_current_test_event(&admin_client);
+    let (user, client) = common::get_logged_in_test_user();
+    common::submit_test_application(&client);
+
+    let response = client
+        .get(format!("{}/applications/pending", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = admin_client
+        .get(format!("{}/applications/pending", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let applications: Value = response.json().unwrap();
+    assert!(applications
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|application| application["id"] == user["id"]));
+}
+
+#[test]
+fn test_confirm_application() {
+    let admin_client = common::get_client_with_logged_in_admin();
+    common::create_current_test_event(&admin_client);
+    let (user, client) = common::get_logged_in_test_user();
+    common::submit_test_application(&client);
+
+    let response = admin_client
+        .put(format!(
+            "{}/applications/{}/confirm",
+            common::APP_HOST,
+            user["id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let application: Value = response.json().unwrap();
+    assert_eq!(application["request"], "confirmed");
+
+    let response = admin_client
+        .put(format!(
+            "{}/applications/{}/reject",
+            common::APP_HOST,
+            user["id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[test]
+fn test_reject_application() {
+    let admin_client = common::get_client_with_logged_in_admin();
+    common::create_current_test_event(&admin_client);
+    let (user, client) = common::get_logged_in_test_user();
+    common::submit_test_application(&client);
+
+    let response = admin_client
+        .put(format!(
+            "{}/applications/{}/reject",
+            common::APP_HOST,
+            user["id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let application: Value = response.json().unwrap();
+    assert_eq!(application["request"], "rejected");
+}
+
+#[test]
+fn test_review_without_application() {
+    let admin_client = common::get_client_with_logged_in_admin();
+    let (user, _client) = common::get_logged_in_test_user();
+
+    let response = admin_client
+        .put(format!(
+            "{}/applications/{}/confirm",
+            common::APP_HOST,
+            user["id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[test]
+fn test_submit_application_requires_verified_email() {
+    let admin_client = common::get_client_with_logged_in_admin();
+    common::create_current_test_event(&admin_client);
+
+    let client = Client::new();
+    let user = common::create_unverified_test_user(&client);
+    let login = common::login(&client, &user["email"], common::TEST_PASSWORD);
+    let client = common::get_client_with_token(&login["token"]);
+
+    let response = client
+        .post(format!("{}/applications", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,479 @@
+use std::num::NonZeroU32;
+
+use data_encoding::HEXLOWER;
+use reqwest::{blocking::Client, StatusCode};
+use ring::pbkdf2;
+use serde_json::{json, Value};
+
+pub mod common;
+
+#[test]
+fn test_register() {
+    let client = Client::new();
+    let email = common::unique_email();
+
+    let response = client
+        .post(format!("{}/auth/register", common::APP_HOST))
+        .json(&json!({
+            "username": "Test User",
+            "email": email,
+            "password": common::TEST_PASSWORD,
+            "phone_number": "+420 123 456 789",
+            "description": "Handmade ceramics",
+            "user_type": "artist"
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let user: Value = response.json().unwrap();
+    assert_eq!(
+        user,
+        json!({
+            "id": user["id"],
+            "username": "Test User",
+            "email": email,
+            "phone_number": "+420 123 456 789",
+            "description": "Handmade ceramics",
+            "user_type": "artist",
+            "role": "user",
+            "event_id": null,
+            "request": "notsend",
+            "image_id": null,
+            "created_at": user["created_at"],
+            "updated_at": user["updated_at"],
+            "disabled_at": null,
+            "email_verified_at": null,
+            "locked_until": null
+        })
+    );
+}
+
+#[test]
+fn test_register_invalid_fields() {
+    let client = Client::new();
+
+    let response = client
+        .post(format!("{}/auth/register", common::APP_HOST))
+        .json(&json!({
+            "username": "",
+            "email": "not-an-email",
+            "password": common::TEST_PASSWORD,
+            "phone_number": "+420 123 456 789",
+            "user_type": "seller"
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(
+        error["details"],
+        json!([
+            { "field": "username", "message": "must not be empty" },
+            { "field": "email", "message": "must be a valid email address" }
+        ])
+    );
+}
+
+#[test]
+fn test_register_duplicate_email() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+
+    let response = client
+        .post(format!("{}/auth/register", common::APP_HOST))
+        .json(&json!({
+            "username": "Another User",
+            "email": user["email"],
+            "password": common::TEST_PASSWORD,
+            "phone_number": "+420 987 654 321",
+            "user_type": "seller"
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(error["code"], "conflict");
+    assert_eq!(error["message"], "Email is already registered");
+}
+
+#[test]
+fn test_login() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+
+    let response = client
+        .post(format!("{}/auth/login", common::APP_HOST))
+        .json(&json!({
+            "email": user["email"],
+            "password": common::TEST_PASSWORD
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let login: Value = response.json().unwrap();
+    assert!(login["token"].is_string());
+    assert!(login["refresh_token"].is_string());
+    assert_eq!(login["expires_in"], 900);
+    assert_eq!(login["user"]["id"], user["id"]);
+    assert!(login["user"].get("password").is_none());
+}
+
+#[test]
+fn test_login_email_case_insensitive() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+    let email = user["email"].as_str().unwrap().to_uppercase();
+
+    let response = client
+        .post(format!("{}/auth/login", common::APP_HOST))
+        .json(&json!({
+            "email": format!(" {} ", email),
+            "password": common::TEST_PASSWORD
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let login: Value = response.json().unwrap();
+    assert_eq!(login["user"]["id"], user["id"]);
+}
+
+/// A hash in the `hexhash.hexsalt` format of the previous backend.
+fn legacy_hash(password: &str) -> String {
+    let salt = [7u8; 16];
+    let mut hash = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(25000).unwrap(),
+        &salt,
+        password.as_bytes(),
+        &mut hash,
+    );
+    format!("{}.{}", HEXLOWER.encode(&hash), HEXLOWER.encode(&salt))
+}
+
+#[test]
+fn test_login_upgrades_legacy_hash() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+    let email = user["email"].as_str().unwrap();
+    let legacy = legacy_hash(common::TEST_PASSWORD);
+    common::set_password_hash(email, &legacy);
+
+    common::login(&client, &user["email"], common::TEST_PASSWORD);
+
+    let upgraded = common::get_password_hash(email);
+    assert_ne!(upgraded, legacy);
+    assert!(upgraded.starts_with("$pbkdf2-sha256$i=600000$"));
+
+    // Log in again, now verifying against the upgraded hash
+    common::login(&client, &user["email"], common::TEST_PASSWORD);
+    assert_eq!(common::get_password_hash(email), upgraded);
+}
+
+#[test]
+fn test_login_wrong_password() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+
+    let response = client
+        .post(format!("{}/auth/login", common::APP_HOST))
+        .json(&json!({
+            "email": user["email"],
+            "password": "wrong_password"
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn test_password_reset() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+    let email = user["email"].as_str().unwrap();
+
+    let response = client
+        .post(format!("{}/auth/password-reset", common::APP_HOST))
+        .json(&json!({ "email": email }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let token = common::read_code_from_latest_email(email);
+
+    let response = client
+        .post(format!("{}/auth/password-reset/confirm", common::APP_HOST))
+        .json(&json!({ "token": token, "password": "new_password" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    common::login(&client, &user["email"], "new_password");
+
+    let response = client
+        .post(format!("{}/auth/login", common::APP_HOST))
+        .json(&json!({ "email": email, "password": common::TEST_PASSWORD }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // The token is single-use.
+    let response = client
+        .post(format!("{}/auth/password-reset/confirm", common::APP_HOST))
+        .json(&json!({ "token": token, "password": "another_password" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn test_password_reset_requires_password() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+    let email = user["email"].as_str().unwrap();
+
+    let response = client
+        .post(format!("{}/auth/password-reset", common::APP_HOST))
+        .json(&json!({ "email": email }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let token = common::read_code_from_latest_email(email);
+
+    let response = client
+        .post(format!("{}/auth/password-reset/confirm", common::APP_HOST))
+        .json(&json!({ "token": token, "password": "" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(
+        error["details"],
+        json!([{ "field": "password", "message": "must not be empty" }])
+    );
+
+    common::login(&client, &user["email"], common::TEST_PASSWORD);
+}
+
+#[test]
+fn test_password_reset_unknown_email() {
+    let client = Client::new();
+
+    let response = client
+        .post(format!("{}/auth/password-reset", common::APP_HOST))
+        .json(&json!({ "email": common::unique_email() }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+}
+
+#[test]
+fn test_verify_email() {
+    let client = Client::new();
+    let user = common::create_unverified_test_user(&client);
+    let email = user["email"].as_str().unwrap();
+    let token = common::read_code_from_latest_email(email);
+
+    let response = client
+        .post(format!("{}/auth/verify-email", common::APP_HOST))
+        .json(&json!({ "token": token }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let verified: Value = response.json().unwrap();
+    assert_eq!(verified["id"], user["id"]);
+    assert!(verified["email_verified_at"].is_string());
+
+    let response = client
+        .post(format!("{}/auth/verify-email", common::APP_HOST))
+        .json(&json!({ "token": token }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn test_resend_verification_email() {
+    let client = Client::new();
+    let user = common::create_unverified_test_user(&client);
+    let email = user["email"].as_str().unwrap();
+    let first_token = common::read_code_from_latest_email(email);
+
+    let login = common::login(&client, &user["email"], common::TEST_PASSWORD);
+    let user_client = common::get_client_with_token(&login["token"]);
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    let response = user_client
+        .post(format!("{}/auth/verify-email/resend", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let token = common::read_code_from_latest_email(email);
+    assert_ne!(token, first_token);
+
+    common::verify_test_user_email(&client, email);
+
+    let response = user_client
+        .post(format!("{}/auth/verify-email/resend", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[test]
+fn test_login_lockout() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+
+    for _ in 0..5 {
+        let response = client
+            .post(format!("{}/auth/login", common::APP_HOST))
+            .json(&json!({
+                "email": user["email"],
+                "password": "wrong_password"
+            }))
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    let response = client
+        .post(format!("{}/auth/login", common::APP_HOST))
+        .json(&json!({
+            "email": user["email"],
+            "password": common::TEST_PASSWORD
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let retry_after: u64 = response.headers()["Retry-After"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 15 * 60 + 1);
+}
+
+#[test]
+fn test_login_lockout_of_unknown_email() {
+    let client = Client::new();
+    let email = common::unique_email();
+
+    for _ in 0..5 {
+        let response = client
+            .post(format!("{}/auth/login", common::APP_HOST))
+            .json(&json!({
+                "email": email,
+                "password": "wrong_password"
+            }))
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    let response = client
+        .post(format!("{}/auth/login", common::APP_HOST))
+        .json(&json!({
+            "email": email,
+            "password": "wrong_password"
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key("Retry-After"));
+}
+
+#[test]
+fn test_refresh() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+    let login = common::login(&client, &user["email"], common::TEST_PASSWORD);
+
+    let response = client
+        .post(format!("{}/auth/refresh", common::APP_HOST))
+        .json(&json!({ "refresh_token": login["refresh_token"] }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let refreshed: Value = response.json().unwrap();
+    assert_ne!(refreshed["refresh_token"], login["refresh_token"]);
+
+    let response = common::get_client_with_token(&refreshed["token"])
+        .get(format!("{}/users/me", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Refresh tokens are rotated, so the old one is no longer accepted.
+    let response = client
+        .post(format!("{}/auth/refresh", common::APP_HOST))
+        .json(&json!({ "refresh_token": login["refresh_token"] }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn test_logout() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+    let login = common::login(&client, &user["email"], common::TEST_PASSWORD);
+    let user_client = common::get_client_with_token(&login["token"]);
+
+    let response = user_client
+        .post(format!("{}/auth/logout", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = user_client
+        .get(format!("{}/users/me", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = client
+        .post(format!("{}/auth/refresh", common::APP_HOST))
+        .json(&json!({ "refresh_token": login["refresh_token"] }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,43 @@
+use diesel::sql_types::Text;
+use diesel::QueryableByName;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+#[derive(QueryableByName)]
+struct PasswordHash {
+    #[diesel(sql_type = Text)]
+    password: String,
+}
+
+/// Connects to the database of the server under test, from `DATABASE_URL`.
+async fn connect() -> AsyncPgConnection {
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL not set");
+
+    AsyncPgConnection::establish(&database_url)
+        .await
+        .expect("Cannot connect to Postgres")
+}
+
+/// Overwrites the stored password hash, e.g. with one in an old format.
+pub fn set_password_hash(email: &str, hash: &str) {
+    let updated = rocket::execute(async {
+        diesel::sql_query("UPDATE users SET password = $1 WHERE email = $2")
+            .bind::<Text, _>(hash)
+            .bind::<Text, _>(email)
+            .execute(&mut connect().await)
+            .await
+            .expect("Cannot update password hash")
+    });
+
+    assert_eq!(updated, 1);
+}
+
+pub fn get_password_hash(email: &str) -> String {
+    rocket::execute(async {
+        diesel::sql_query("SELECT password FROM users WHERE email = $1")
+            .bind::<Text, _>(email)
+            .get_result::<PasswordHash>(&mut connect().await)
+            .await
+            .expect("Cannot load password hash")
+            .password
+    })
+}
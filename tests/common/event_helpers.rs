@@ -19,6 +19,22 @@ pub fn create_test_event(client: &Client) -> Value {
     response.json().unwrap()
 }
 
+/// Creates a current event far enough in the future to be picked over other test events.
+pub fn create_current_test_event(client: &Client) -> Value {
+    let response = client
+        .post(format!("{}/events", APP_HOST))
+        .json(&json!({
+         "title": "Future Festival",
+         "year": 2100,
+         "is_current": true
+        }))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    response.json().unwrap()
+}
+
 pub fn create_test_program_for_event(client: &Client, event: &Value) -> Value {
     let buffer = load_test_image();
 
@@ -0,0 +1,70 @@
+use std::process::Command;
+
+use reqwest::blocking::{multipart, Client};
+use reqwest::StatusCode;
+use serde_json::Value;
+
+use super::utils::{load_test_image, APP_HOST};
+
+pub fn upload_test_image(client: &Client) -> Value {
+    let buffer = load_test_image();
+
+    let part = multipart::Part::bytes(buffer)
+        .file_name("test.image.jpg")
+        .mime_str("image/jpeg")
+        .expect("Failed to create part");
+
+    let form = multipart::Form::new().part("image", part);
+
+    let response = client
+        .post(format!("{}/images", APP_HOST))
+        .multipart(form)
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    response.json().unwrap()
+}
+
+/// A file that claims to be a JPEG but cannot be decoded.
+pub fn corrupt_image_part() -> multipart::Part {
+    multipart::Part::bytes(b"definitely not a jpeg".to_vec())
+        .file_name("corrupt.jpg")
+        .mime_str("image/jpeg")
+        .expect("Failed to create part")
+}
+
+pub fn delete_test_image(client: &Client, image: Value) {
+    let response = client
+        .delete(format!("{}/images/{}", APP_HOST, image["id"]))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+pub fn assert_image_deleted(client: &Client, image_id: &Value) {
+    let response = client
+        .get(format!("{}/images/{}", APP_HOST, image_id))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Runs the `gc-images` command and returns what it printed.
+pub fn run_gc_images(args: &[&str]) -> String {
+    let output = Command::new("cargo")
+        .args(["run", "--quiet", "--", "gc-images"])
+        .args(args)
+        .output()
+        .expect("Failed to run gc-images command");
+    assert!(
+        output.status.success(),
+        "gc-images failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).unwrap()
+}
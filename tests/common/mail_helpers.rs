@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+fn outbox_dir() -> PathBuf {
+    std::env::var("MAILER_OUTBOX_DIR")
+        .unwrap_or_else(|_| "mail_outbox".to_string())
+        .into()
+}
+
+/// Returns the most recent email sent to `to` by the file mailer.
+pub fn read_latest_email(to: &str) -> String {
+    let header = format!("To: {}\n", to);
+
+    for _ in 0..20 {
+        let latest = fs::read_dir(outbox_dir())
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let modified = entry.metadata().and_then(|m| m.modified()).ok();
+                let content = fs::read_to_string(entry.path()).ok()?;
+                content.starts_with(&header).then_some((modified, content))
+            })
+            .max_by_key(|(modified, _)| *modified);
+
+        if let Some((_, content)) = latest {
+            return content;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    panic!("No email sent to {}", to);
+}
+
+/// Extracts the code from the line of the latest email that reads `...code...: <code>`.
+pub fn read_code_from_latest_email(to: &str) -> String {
+    read_latest_email(to)
+        .lines()
+        .find(|line| line.contains("code"))
+        .and_then(|line| line.rsplit(": ").next())
+        .expect("Email does not contain a code")
+        .trim()
+        .to_string()
+}
@@ -1,9 +1,17 @@
+mod db_helpers;
 mod event_helpers;
 mod gallery_helpers;
+mod image_helpers;
+mod mail_helpers;
 mod news_helpers;
+mod user_helpers;
 mod utils;
 
+pub use db_helpers::*;
 pub use event_helpers::*;
 pub use gallery_helpers::*;
+pub use image_helpers::*;
+pub use mail_helpers::*;
 pub use news_helpers::*;
+pub use user_helpers::*;
 pub use utils::*;
@@ -0,0 +1,147 @@
+use std::process::Command;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use reqwest::blocking::Client;
+use reqwest::header;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use super::mail_helpers::read_code_from_latest_email;
+use super::utils::APP_HOST;
+
+pub static TEST_PASSWORD: &str = "test_password";
+
+static EMAIL_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+pub fn unique_email() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let counter = EMAIL_COUNTER.fetch_add(1, Ordering::SeqCst);
+    format!("test_{}_{}@example.com", nanos, counter)
+}
+
+pub fn create_test_user(client: &Client) -> Value {
+    let email = unique_email();
+    let username = email.split('@').next().unwrap();
+
+    let response = client
+        .post(format!("{}/auth/register", APP_HOST))
+        .json(&json!({
+            "username": username,
+            "email": email,
+            "password": TEST_PASSWORD,
+            "phone_number": "+420 123 456 789",
+            "description": "Handmade ceramics",
+            "user_type": "seller"
+        }))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    verify_test_user_email(client, &email)
+}
+
+pub fn create_unverified_test_user(client: &Client) -> Value {
+    let response = client
+        .post(format!("{}/auth/register", APP_HOST))
+        .json(&json!({
+            "username": "Unverified User",
+            "email": unique_email(),
+            "password": TEST_PASSWORD,
+            "phone_number": "+420 123 456 789",
+            "user_type": "artist"
+        }))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    response.json().unwrap()
+}
+
+pub fn verify_test_user_email(client: &Client, email: &str) -> Value {
+    let token = read_code_from_latest_email(email);
+
+    let response = client
+        .post(format!("{}/auth/verify-email", APP_HOST))
+        .json(&json!({ "token": token }))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    response.json().unwrap()
+}
+
+pub fn login(client: &Client, email: &Value, password: &str) -> Value {
+    let response = client
+        .post(format!("{}/auth/login", APP_HOST))
+        .json(&json!({
+            "email": email,
+            "password": password
+        }))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    response.json().unwrap()
+}
+
+pub fn get_client_with_token(token: &Value) -> Client {
+    let mut headers = header::HeaderMap::new();
+    headers.insert(
+        header::AUTHORIZATION,
+        header::HeaderValue::from_str(&format!("Bearer {}", token.as_str().unwrap())).unwrap(),
+    );
+
+    Client::builder().default_headers(headers).build().unwrap()
+}
+
+pub fn get_client_with_logged_in_user() -> Client {
+    get_logged_in_test_user().1
+}
+
+pub fn get_logged_in_test_user() -> (Value, Client) {
+    let client = Client::new();
+    let user = create_test_user(&client);
+    let login = login(&client, &user["email"], TEST_PASSWORD);
+
+    (user, get_client_with_token(&login["token"]))
+}
+
+pub fn create_test_admin() -> String {
+    let email = unique_email();
+
+    let output = Command::new("cargo")
+        .args(["run", "--quiet", "--", "create-admin", "Test Admin"])
+        .arg(&email)
+        .arg(TEST_PASSWORD)
+        .output()
+        .expect("Failed to run create-admin command");
+    assert!(
+        output.status.success(),
+        "create-admin failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    email
+}
+
+pub fn get_client_with_logged_in_admin() -> Client {
+    let client = Client::new();
+    let email = create_test_admin();
+    let login = login(&client, &json!(email), TEST_PASSWORD);
+
+    get_client_with_token(&login["token"])
+}
+
+pub fn submit_test_application(client: &Client) -> Value {
+    let response = client
+        .post(format!("{}/applications", APP_HOST))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    response.json().unwrap()
+}
@@ -1,11 +1,27 @@
 use std::fs::File;
 use std::io::Read;
+use std::path::PathBuf;
 
 pub static APP_HOST: &str = "http://0.0.0.0:8000";
 
+/// Where the server under test keeps its files with `STORAGE_BACKEND=local`.
+pub fn local_storage_dir() -> PathBuf {
+    std::env::var("LOCAL_STORAGE_DIR")
+        .unwrap_or_else(|_| "storage".to_string())
+        .into()
+}
+
 pub fn load_test_image() -> Vec<u8> {
     let mut file = File::open("./tests/assets/test_image.jpg").expect("Failed to open file");
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer).expect("Failed to read file");
     buffer
 }
+
+/// A much smaller photo, for tests that upload several at once.
+pub fn load_small_test_image() -> Vec<u8> {
+    let mut file = File::open("./tests/assets/black-widow.jpg").expect("Failed to open file");
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer).expect("Failed to read file");
+    buffer
+}
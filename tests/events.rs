@@ -5,7 +5,7 @@ pub mod common;
 
 #[test]
 fn test_create_event() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
 
     let response = client
         .post(format!("{}/events", common::APP_HOST))
@@ -23,8 +23,56 @@ fn test_create_event() {
 }
 
 #[test]
-fn test_get_event() {
+fn test_create_event_unauthorized() {
     let client = Client::new();
+
+    let response = client
+        .post(format!("{}/events", common::APP_HOST))
+        .json(&json!({
+         "title": "My New Event",
+         "year": 2025,
+         "is_current": true
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let client = common::get_client_with_token(&json!("1.99999999999.invalid-signature"));
+
+    let response = client
+        .post(format!("{}/events", common::APP_HOST))
+        .json(&json!({
+         "title": "My New Event",
+         "year": 2025,
+         "is_current": true
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn test_create_event_forbidden_for_regular_user() {
+    let client = common::get_client_with_logged_in_user();
+
+    let response = client
+        .post(format!("{}/events", common::APP_HOST))
+        .json(&json!({
+         "title": "My New Event",
+         "year": 2025,
+         "is_current": true
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn test_get_event() {
+    let client = common::get_client_with_logged_in_admin();
     let event = common::create_test_event(&client);
 
     let response = client
@@ -51,8 +99,60 @@ fn test_get_event() {
 }
 
 #[test]
-fn test_update_event() {
+fn test_create_event_invalid_fields() {
+    let client = common::get_client_with_logged_in_admin();
+
+    let response = client
+        .post(format!("{}/events", common::APP_HOST))
+        .json(&json!({
+         "title": "x".repeat(129),
+         "year": 1800,
+         "is_current": false
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(
+        error,
+        json!({
+            "code": "invalid_fields",
+            "message": "Some fields are invalid",
+            "details": [
+                { "field": "title", "message": "must be at most 128 characters long" },
+                { "field": "year", "message": "must be between 2000 and 2100" }
+            ]
+        })
+    );
+}
+
+#[test]
+fn test_get_missing_event() {
     let client = Client::new();
+
+    let response = client
+        .get(format!("{}/events/{}", common::APP_HOST, i32::MAX))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(
+        error,
+        json!({
+            "code": "not_found",
+            "message": "Not found",
+            "details": null
+        })
+    );
+}
+
+#[test]
+fn test_update_event() {
+    let client = common::get_client_with_logged_in_admin();
     let event = common::create_test_event(&client);
 
     let response = client
@@ -86,7 +186,7 @@ fn test_update_event() {
 
 #[test]
 fn test_create_event_with_program() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let event = common::create_test_event(&client);
     let program = common::create_test_program_for_event(&client, &event);
 
@@ -99,7 +199,7 @@ fn test_create_event_with_program() {
 
 #[test]
 fn test_get_event_with_program() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let (event, program) = common::create_test_event_with_program(&client);
 
     let response = client
@@ -132,9 +232,78 @@ fn test_get_event_with_program() {
     common::delete_test_event(&client, event);
 }
 
+#[test]
+fn test_get_event_participants() {
+    let admin_client = common::get_client_with_logged_in_admin();
+    common::create_current_test_event(&admin_client);
+
+    let (confirmed_user, client) = common::get_logged_in_test_user();
+    let application = common::submit_test_application(&client);
+    let (pending_user, client) = common::get_logged_in_test_user();
+    common::submit_test_application(&client);
+
+    let response = admin_client
+        .put(format!(
+            "{}/applications/{}/confirm",
+            common::APP_HOST,
+            confirmed_user["id"]
+        ))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = Client::new()
+        .get(format!(
+            "{}/events/{}/participants",
+            common::APP_HOST,
+            application["event_id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let participants: Value = response.json().unwrap();
+    let participants = participants.as_array().unwrap();
+    let participant = participants
+        .iter()
+        .find(|participant| participant["username"] == confirmed_user["username"])
+        .expect("Confirmed user is not listed");
+    assert_eq!(
+        participant,
+        &json!({
+            "username": confirmed_user["username"],
+            "description": "Handmade ceramics",
+            "user_type": "seller",
+            "image_url": null
+        })
+    );
+    assert!(!participants
+        .iter()
+        .any(|participant| participant["username"] == pending_user["username"]));
+
+    let response = Client::new()
+        .get(format!(
+            "{}/events/{}/participants?user_type=artist",
+            common::APP_HOST,
+            application["event_id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let artists: Value = response.json().unwrap();
+    assert!(!artists
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|participant| participant["username"] == confirmed_user["username"]));
+}
+
 #[test]
 fn test_delete_event() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let event = common::create_test_event(&client);
 
     let response = client
@@ -144,3 +313,22 @@ fn test_delete_event() {
 
     assert_eq!(response.status(), StatusCode::NO_CONTENT);
 }
+
+#[test]
+fn test_delete_event_with_program() {
+    let client = common::get_client_with_logged_in_admin();
+    let (event, program) = common::create_test_event_with_program(&client);
+
+    let response = client
+        .delete(format!("{}/events/{}", common::APP_HOST, event["id"]))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(error["message"], "Event still has programs or participants");
+
+    common::delete_test_program(&client, program);
+    common::delete_test_event(&client, event);
+}
@@ -1,4 +1,4 @@
-use reqwest::blocking::{multipart, Client};
+use reqwest::blocking::multipart;
 use reqwest::StatusCode;
 use serde_json::{json, Value};
 
@@ -6,7 +6,7 @@ pub mod common;
 
 #[test]
 fn test_create_gallery() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
 
     let buffer = common::load_test_image();
 
@@ -43,9 +43,29 @@ fn test_create_gallery() {
     common::delete_test_gallery(&client, gallery);
 }
 
+#[test]
+fn test_create_gallery_with_corrupt_image() {
+    let client = common::get_client_with_logged_in_admin();
+
+    let form = multipart::Form::new()
+        .text("name", "Test Gallery Name")
+        .part("image", common::corrupt_image_part());
+
+    let response = client
+        .post(format!("{}/gallery", common::APP_HOST))
+        .multipart(form)
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(error["code"], "validation_failed");
+}
+
 #[test]
 fn test_get_gallery() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let gallery = common::create_test_gallery(&client);
 
     let response = client
@@ -64,7 +84,7 @@ fn test_get_gallery() {
 
 #[test]
 fn test_get_all_galleries() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let gallery_1 = common::create_test_gallery(&client);
     let gallery_2 = common::create_test_gallery(&client);
 
@@ -85,7 +105,7 @@ fn test_get_all_galleries() {
 
 #[test]
 fn test_update_gallery() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let gallery = common::create_test_gallery(&client);
 
     // change only name
@@ -103,14 +123,55 @@ fn test_update_gallery() {
 
     assert_eq!(gallery["name"], "Updated Gallery Name");
 
-    common::delete_test_gallery(&client, gallery);
+    // change the featured image
+    let part = multipart::Part::bytes(common::load_small_test_image())
+        .file_name("test.image.jpg")
+        .mime_str("image/jpeg")
+        .expect("Failed to create part");
+    let form = multipart::Form::new().part("image", part);
+
+    let response = client
+        .put(format!("{}/gallery/{}", common::APP_HOST, gallery["id"]))
+        .multipart(form)
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let updated: Value = response.json().expect("Failed to parse JSON response");
+
+    assert_ne!(updated["featured_image_id"], gallery["featured_image_id"]);
+    common::assert_image_deleted(&client, &gallery["featured_image_id"]);
+
+    common::delete_test_gallery(&client, updated);
 }
 
 #[test]
 fn test_delete_gallery() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let gallery = common::create_test_gallery(&client);
 
+    let part = multipart::Part::bytes(common::load_small_test_image())
+        .file_name("test.image.jpg")
+        .mime_str("image/jpeg")
+        .expect("Failed to create part");
+    let form = multipart::Form::new().part("image", part);
+
+    let response = client
+        .post(format!(
+            "{}/gallery/{}/images",
+            common::APP_HOST,
+            gallery["id"]
+        ))
+        .multipart(form)
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = response.json().expect("Failed to parse JSON response");
+    let image_id = body["images"][0]["id"].clone();
+
     let response = client
         .delete(format!("{}/gallery/{}", common::APP_HOST, gallery["id"]))
         .send()
@@ -121,4 +182,224 @@ fn test_delete_gallery() {
         StatusCode::NO_CONTENT,
         "Response was not 204 NO CONTENT"
     );
+
+    common::assert_image_deleted(&client, &gallery["featured_image_id"]);
+    common::assert_image_deleted(&client, &image_id);
+}
+
+#[test]
+fn test_update_gallery_without_fields() {
+    let client = common::get_client_with_logged_in_admin();
+    let gallery = common::create_test_gallery(&client);
+
+    let response = client
+        .put(format!("{}/gallery/{}", common::APP_HOST, gallery["id"]))
+        .json(&json!({}))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>().unwrap(), gallery);
+
+    common::delete_test_gallery(&client, gallery);
+}
+
+#[test]
+fn test_create_gallery_json_requires_featured_image() {
+    let client = common::get_client_with_logged_in_admin();
+
+    let response = client
+        .post(format!("{}/gallery", common::APP_HOST))
+        .json(&json!({ "name": "Test Gallery" }))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(
+        error["details"],
+        json!([{ "field": "featured_image_id", "message": "is required" }])
+    );
+}
+
+#[test]
+fn test_get_gallery_includes_images() {
+    let client = common::get_client_with_logged_in_admin();
+    let gallery = common::create_test_gallery(&client);
+
+    let response = client
+        .get(format!("{}/gallery/{}", common::APP_HOST, gallery["id"]))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = response.json().expect("Failed to parse JSON response");
+    assert_eq!(body["id"], gallery["id"]);
+    assert_eq!(body["images"], json!([]));
+
+    common::delete_test_gallery(&client, gallery);
+}
+
+#[test]
+fn test_get_missing_gallery() {
+    let client = common::get_client_with_logged_in_admin();
+
+    let response = client
+        .get(format!("{}/gallery/0", common::APP_HOST))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_add_and_remove_gallery_images() {
+    let client = common::get_client_with_logged_in_admin();
+    let gallery = common::create_test_gallery(&client);
+
+    let image_part = || {
+        multipart::Part::bytes(common::load_small_test_image())
+            .file_name("test.image.jpg")
+            .mime_str("image/jpeg")
+            .expect("Failed to create part")
+    };
+    let form = multipart::Form::new()
+        .part("image", image_part())
+        .part("image", image_part());
+
+    let response = client
+        .post(format!(
+            "{}/gallery/{}/images",
+            common::APP_HOST,
+            gallery["id"]
+        ))
+        .multipart(form)
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: Value = response.json().expect("Failed to parse JSON response");
+    let image_ids: Vec<Value> = body["images"]
+        .as_array()
+        .expect("images should be an array")
+        .iter()
+        .map(|image| image["id"].clone())
+        .collect();
+    assert_eq!(image_ids.len(), 2);
+
+    let response = client
+        .delete(format!(
+            "{}/gallery/{}/images",
+            common::APP_HOST,
+            gallery["id"]
+        ))
+        .json(&json!({ "image_ids": image_ids }))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client
+        .get(format!("{}/gallery/{}", common::APP_HOST, gallery["id"]))
+        .send()
+        .expect("Failed to send request");
+    let body: Value = response.json().expect("Failed to parse JSON response");
+    assert_eq!(body["images"], json!([]));
+
+    let response = client
+        .get(format!("{}/images/{}", common::APP_HOST, image_ids[0]))
+        .send()
+        .expect("Failed to send request");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    common::delete_test_gallery(&client, gallery);
+}
+
+#[test]
+fn test_add_too_many_gallery_images() {
+    let client = common::get_client_with_logged_in_admin();
+    let gallery = common::create_test_gallery(&client);
+
+    let image = common::load_small_test_image();
+    let form = (0..21).fold(multipart::Form::new(), |form, _| {
+        let part = multipart::Part::bytes(image.clone())
+            .file_name("test.image.jpg")
+            .mime_str("image/jpeg")
+            .expect("Failed to create part");
+        form.part("image", part)
+    });
+
+    let response = client
+        .post(format!(
+            "{}/gallery/{}/images",
+            common::APP_HOST,
+            gallery["id"]
+        ))
+        .multipart(form)
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(
+        error["details"],
+        json!([{ "field": "image", "message": "accepts at most 20 files" }])
+    );
+
+    let response = client
+        .get(format!("{}/gallery/{}", common::APP_HOST, gallery["id"]))
+        .send()
+        .expect("Failed to send request");
+    let body: Value = response.json().expect("Failed to parse JSON response");
+    assert_eq!(body["images"], json!([]));
+
+    common::delete_test_gallery(&client, gallery);
+}
+
+#[test]
+fn test_remove_image_not_in_gallery() {
+    let client = common::get_client_with_logged_in_admin();
+    let gallery = common::create_test_gallery(&client);
+    let image = common::upload_test_image(&client);
+
+    let response = client
+        .delete(format!(
+            "{}/gallery/{}/images",
+            common::APP_HOST,
+            gallery["id"]
+        ))
+        .json(&json!({ "image_ids": [image["id"]] }))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(error["details"][0]["field"], "image_ids");
+
+    let response = client
+        .get(format!("{}/images/{}", common::APP_HOST, image["id"]))
+        .send()
+        .expect("Failed to send request");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    common::delete_test_image(&client, image);
+    common::delete_test_gallery(&client, gallery);
+}
+
+#[test]
+fn test_remove_images_from_missing_gallery() {
+    let client = common::get_client_with_logged_in_admin();
+
+    let response = client
+        .delete(format!("{}/gallery/0/images", common::APP_HOST))
+        .json(&json!({ "image_ids": [1] }))
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
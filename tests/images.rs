@@ -0,0 +1,249 @@
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+pub mod common;
+
+#[test]
+fn test_upload_image() {
+    let client = common::get_client_with_logged_in_admin();
+    let image = common::upload_test_image(&client);
+
+    assert!(image["id"].is_number());
+    assert!(image["image_url"].is_string());
+    assert!(image["width"].as_i64().unwrap() > 0);
+    assert!(image["height"].as_i64().unwrap() > 0);
+
+    let variants = image["variants"].as_array().unwrap();
+    assert!(variants
+        .iter()
+        .any(|v| v["name"] == "thumbnail" && v["format"] == "webp"));
+    let widths: Vec<_> = variants
+        .iter()
+        .map(|v| v["width"].as_i64().unwrap())
+        .collect();
+    assert!(widths.windows(2).all(|pair| pair[0] <= pair[1]));
+
+    let response = client
+        .get(format!("{}/images/{}", common::APP_HOST, image["id"]))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let fetched: Value = response.json().unwrap();
+    assert_eq!(fetched, image);
+}
+
+#[test]
+fn test_delete_image_forbidden_for_regular_user() {
+    let client = common::get_client_with_logged_in_user();
+
+    let response = client
+        .delete(format!("{}/images/{}", common::APP_HOST, i32::MAX))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn test_get_images() {
+    let client = common::get_client_with_logged_in_admin();
+    let image = common::upload_test_image(&client);
+
+    let response = Client::new()
+        .get(format!("{}/images?per_page=1", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let page: Value = response.json().unwrap();
+    assert_eq!(page["page"], 1);
+    assert_eq!(page["per_page"], 1);
+    assert_eq!(page["images"].as_array().unwrap().len(), 1);
+    assert!(page["total"].as_i64().unwrap() >= 1);
+
+    let response = Client::new()
+        .get(format!("{}/images?per_page=101", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    common::delete_test_image(&client, image);
+}
+
+#[test]
+fn test_get_missing_image() {
+    let response = Client::new()
+        .get(format!("{}/images/{}", common::APP_HOST, i32::MAX))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_delete_image() {
+    let client = common::get_client_with_logged_in_admin();
+    let image = common::upload_test_image(&client);
+
+    let response = client
+        .delete(format!("{}/images/{}", common::APP_HOST, image["id"]))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client
+        .get(format!("{}/images/{}", common::APP_HOST, image["id"]))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_delete_referenced_image() {
+    let client = common::get_client_with_logged_in_admin();
+    let image = common::upload_test_image(&client);
+
+    let response = client
+        .post(format!("{}/news", common::APP_HOST))
+        .json(&json!({
+            "title": "Test News Title",
+            "message": "This is great news",
+            "image_id": image["id"]
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let news: Value = response.json().unwrap();
+
+    let response = client
+        .delete(format!("{}/images/{}", common::APP_HOST, image["id"]))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    common::delete_test_news(&client, news);
+}
+
+#[test]
+fn test_gc_images_keeps_library_images() {
+    let client = common::get_client_with_logged_in_admin();
+    let image = common::upload_test_image(&client);
+    assert_eq!(image["in_library"], true);
+    let listed = format!("  #{} ", image["id"]);
+
+    let report = common::run_gc_images(&["--dry-run", "--grace-hours", "0"]);
+
+    assert!(report.starts_with("Would delete"));
+    assert!(!report.contains(&listed));
+
+    // Nor does a news that used the image take it along
+    let response = client
+        .post(format!("{}/news", common::APP_HOST))
+        .json(&json!({
+            "title": "Test News Title",
+            "message": "This is great news",
+            "image_id": image["id"]
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let news: Value = response.json().unwrap();
+    common::delete_test_news(&client, news);
+
+    let response = client
+        .get(format!("{}/images/{}", common::APP_HOST, image["id"]))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    common::delete_test_image(&client, image);
+}
+
+#[test]
+fn test_presign_upload_validation() {
+    let client = common::get_client_with_logged_in_admin();
+
+    let response = client
+        .post(format!("{}/images/presign", common::APP_HOST))
+        .json(&json!({ "file_name": "", "content_type": "text/plain" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let error: Value = response.json().unwrap();
+    let fields: Vec<_> = error["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, vec!["file_name", "content_type"]);
+}
+
+#[test]
+fn test_complete_upload_rejects_unknown_keys() {
+    let client = common::get_client_with_logged_in_admin();
+
+    let response = client
+        .post(format!("{}/images/complete", common::APP_HOST))
+        .json(&json!({ "key": "../secrets.txt" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = client
+        .post(format!("{}/images/complete", common::APP_HOST))
+        .json(&json!({ "key": "uploads/0b8e4a52-6f0e-4b8e-9a43-3c1f5c1c2b7d.jpg" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn test_complete_upload_rejects_too_large_object() {
+    let client = common::get_client_with_logged_in_admin();
+    let key = format!("uploads/{}.jpg", uuid::Uuid::new_v4());
+    let path = common::local_storage_dir().join(&key);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    // Sparse, so the test does not actually write 30 MB
+    let file = std::fs::File::create(&path).unwrap();
+    file.set_len(30 * 1024 * 1024 + 1).unwrap();
+
+    let response = client
+        .post(format!("{}/images/complete", common::APP_HOST))
+        .json(&json!({ "key": key }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_presign_upload_forbidden_for_regular_user() {
+    let client = common::get_client_with_logged_in_user();
+
+    let response = client
+        .post(format!("{}/images/presign", common::APP_HOST))
+        .json(&json!({ "file_name": "a.jpg", "content_type": "image/jpeg" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
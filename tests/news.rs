@@ -1,7 +1,7 @@
 use std::fs::File;
 use std::io::Read;
 
-use reqwest::blocking::{multipart, Client};
+use reqwest::blocking::multipart;
 use reqwest::StatusCode;
 use serde_json::{json, Value};
 
@@ -9,7 +9,7 @@ pub mod common;
 
 #[test]
 fn test_create_news() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
 
     let buffer = common::load_test_image();
 
@@ -48,9 +48,30 @@ fn test_create_news() {
     common::delete_test_news(&client, news);
 }
 
+#[test]
+fn test_create_news_with_corrupt_image() {
+    let client = common::get_client_with_logged_in_admin();
+
+    let form = multipart::Form::new()
+        .text("title", "Test News Title")
+        .text("message", "This is great news")
+        .part("image", common::corrupt_image_part());
+
+    let response = client
+        .post(format!("{}/news", common::APP_HOST))
+        .multipart(form)
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(error["code"], "validation_failed");
+}
+
 #[test]
 fn test_get_single_news() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let news = common::create_test_news(&client);
 
     let response = client
@@ -79,7 +100,7 @@ fn test_get_single_news() {
 
 #[test]
 fn test_get_all_news() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let news_one = common::create_test_news(&client);
     let news_two = common::create_test_news(&client);
 
@@ -100,7 +121,7 @@ fn test_get_all_news() {
 
 #[test]
 fn test_update_news() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let news = common::create_test_news(&client);
 
     // change only title
@@ -150,15 +171,16 @@ fn test_update_news() {
 
     let news: Value = response.json().unwrap();
 
-    // assert that the image_id has changed
+    // assert that the image_id has changed and the old image is gone
     assert_ne!(old_news["image_id"], news["image_id"]);
+    common::assert_image_deleted(&client, &old_news["image_id"]);
 
     //common::delete_test_news(&client, news);
 }
 
 #[test]
 fn test_delete_news() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let news = common::create_test_news(&client);
 
     let response = client
@@ -167,4 +189,170 @@ fn test_delete_news() {
         .unwrap();
 
     assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    common::assert_image_deleted(&client, &news["image_id"]);
+}
+
+#[test]
+fn test_create_news_invalid_fields() {
+    let client = common::get_client_with_logged_in_admin();
+
+    let form = multipart::Form::new().text("title", "x".repeat(300));
+
+    let response = client
+        .post(format!("{}/news", common::APP_HOST))
+        .multipart(form)
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(error["code"], "invalid_fields");
+
+    let mut fields: Vec<&str> = error["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|detail| detail["field"].as_str().unwrap())
+        .collect();
+    fields.sort();
+    assert_eq!(fields, vec!["image", "message", "title"]);
+}
+
+#[test]
+fn test_update_news_json() {
+    let client = common::get_client_with_logged_in_admin();
+    let news = common::create_test_news(&client);
+
+    let response = client
+        .put(format!("{}/news/{}", common::APP_HOST, news["id"]))
+        .json(&json!({ "title": "Updated News Title" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let updated: Value = response.json().unwrap();
+    assert_eq!(updated["title"], "Updated News Title");
+    assert_eq!(updated["message"], news["message"]);
+    assert_eq!(updated["image_id"], news["image_id"]);
+
+    let response = client
+        .post(format!("{}/news", common::APP_HOST))
+        .json(&json!({
+            "title": "Reused Image",
+            "message": "Shares the image of another news",
+            "image_id": news["image_id"]
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let created: Value = response.json().unwrap();
+    assert_eq!(created["image_id"], news["image_id"]);
+
+    // The shared image outlives the first news and goes with the second.
+    common::delete_test_news(&client, news);
+    common::delete_test_news(&client, created);
+}
+
+#[test]
+fn test_update_news_without_fields() {
+    let client = common::get_client_with_logged_in_admin();
+    let news = common::create_test_news(&client);
+
+    let response = client
+        .put(format!("{}/news/{}", common::APP_HOST, news["id"]))
+        .json(&json!({}))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>().unwrap(), news);
+
+    common::delete_test_news(&client, news);
+}
+
+#[test]
+fn test_update_news_json_releases_replaced_image() {
+    let client = common::get_client_with_logged_in_admin();
+    let news = common::create_test_news(&client);
+    let image = common::upload_test_image(&client);
+
+    let response = client
+        .post(format!("{}/news", common::APP_HOST))
+        .json(&json!({
+            "title": "Reused Image",
+            "message": "Shares the image of another news",
+            "image_id": news["image_id"]
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let other: Value = response.json().unwrap();
+
+    for updated in [&news, &other] {
+        let response = client
+            .put(format!("{}/news/{}", common::APP_HOST, updated["id"]))
+            .json(&json!({ "image_id": image["id"] }))
+            .send()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    // Kept while the other news used it, deleted with the second update
+    common::assert_image_deleted(&client, &news["image_id"]);
+
+    common::delete_test_news(&client, news);
+    common::delete_test_news(&client, other);
+
+    // The library image outlives the news that used it
+    common::delete_test_image(&client, image);
+}
+
+#[test]
+fn test_create_news_json_with_unknown_image() {
+    let client = common::get_client_with_logged_in_admin();
+
+    let response = client
+        .post(format!("{}/news", common::APP_HOST))
+        .json(&json!({
+            "title": "Test News Title",
+            "message": "This is great news",
+            "image_id": i32::MAX
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(error["code"], "invalid_reference");
+}
+
+#[test]
+fn test_create_news_json_invalid_fields() {
+    let client = common::get_client_with_logged_in_admin();
+
+    let response = client
+        .post(format!("{}/news", common::APP_HOST))
+        .json(&json!({ "title": 42, "message": "This is great news" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(
+        error["details"],
+        json!([
+            { "field": "title", "message": "must be a string" },
+            { "field": "image_id", "message": "is required" }
+        ])
+    );
 }
@@ -1,4 +1,4 @@
-use reqwest::blocking::{multipart, Client};
+use reqwest::blocking::multipart;
 use reqwest::StatusCode;
 use serde_json::{json, Value};
 
@@ -6,7 +6,7 @@ pub mod common;
 
 #[test]
 fn test_create_program() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let event = common::create_test_event(&client);
 
     let buffer = common::load_test_image();
@@ -55,9 +55,34 @@ fn test_create_program() {
     common::delete_test_event(&client, event);
 }
 
+#[test]
+fn test_create_program_with_corrupt_image() {
+    let client = common::get_client_with_logged_in_admin();
+    let event = common::create_test_event(&client);
+
+    let form = multipart::Form::new()
+        .text("title", "Test Program Title")
+        .text("text", "Some program text")
+        .text("event_id", event["id"].as_i64().unwrap().to_string())
+        .part("image", common::corrupt_image_part());
+
+    let response = client
+        .post(format!("{}/programs", common::APP_HOST))
+        .multipart(form)
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let error: Value = response.json().unwrap();
+    assert_eq!(error["code"], "validation_failed");
+
+    common::delete_test_event(&client, event);
+}
+
 #[test]
 fn get_programs_for_event() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let (event, _program) = common::create_test_event_with_program(&client);
 
     let response = client
@@ -90,9 +115,28 @@ fn get_programs_for_event() {
     common::delete_test_event(&client, event);
 }
 
+#[test]
+fn test_update_program_without_fields() {
+    let client = common::get_client_with_logged_in_admin();
+    let event = common::create_test_event(&client);
+    let program = common::create_test_program_for_event(&client, &event);
+
+    let response = client
+        .put(format!("{}/programs/{}", common::APP_HOST, program["id"]))
+        .json(&json!({}))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>().unwrap(), program);
+
+    common::delete_test_program(&client, program);
+    common::delete_test_event(&client, event);
+}
+
 #[test]
 fn test_update_program() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let event = common::create_test_event(&client);
     let program = common::create_test_program_for_event(&client, &event);
 
@@ -134,6 +178,8 @@ fn test_update_program() {
             "updated_at": updated_program["updated_at"]
         })
     );
+    assert_ne!(updated_program["image_id"], program["image_id"]);
+    common::assert_image_deleted(&client, &program["image_id"]);
 
     common::delete_test_program(&client, updated_program);
     common::delete_test_event(&client, event);
@@ -141,7 +187,7 @@ fn test_update_program() {
 
 #[test]
 fn test_delete_program() {
-    let client = Client::new();
+    let client = common::get_client_with_logged_in_admin();
     let event = common::create_test_event(&client);
     let program = common::create_test_program_for_event(&client, &event);
 
@@ -156,5 +202,7 @@ fn test_delete_program() {
         "Response was not 204 No Content"
     );
 
+    common::assert_image_deleted(&client, &program["image_id"]);
+
     common::delete_test_event(&client, event);
 }
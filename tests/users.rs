@@ -0,0 +1,236 @@
+use reqwest::blocking::{multipart, Client};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+pub mod common;
+
+#[test]
+fn test_update_own_profile() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+    let login = common::login(&client, &user["email"], common::TEST_PASSWORD);
+    let client = common::get_client_with_token(&login["token"]);
+
+    let response = client
+        .put(format!("{}/users/{}", common::APP_HOST, user["id"]))
+        .json(&json!({
+            "username": "Renamed User",
+            "description": "Wooden toys"
+        }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let updated: Value = response.json().unwrap();
+    assert_eq!(updated["username"], "Renamed User");
+    assert_eq!(updated["description"], "Wooden toys");
+    assert_eq!(updated["phone_number"], user["phone_number"]);
+}
+
+#[test]
+fn test_update_profile_without_fields() {
+    let (user, client) = common::get_logged_in_test_user();
+
+    let response = client
+        .put(format!("{}/users/{}", common::APP_HOST, user["id"]))
+        .json(&json!({}))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>().unwrap(), user);
+
+    let response = client
+        .put(format!("{}/users/me", common::APP_HOST))
+        .multipart(multipart::Form::new().text("email", common::unique_email()))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json::<Value>().unwrap(), user);
+}
+
+#[test]
+fn test_update_other_profile_forbidden() {
+    let client = common::get_client_with_logged_in_user();
+    let other_user = common::create_test_user(&Client::new());
+
+    let response = client
+        .put(format!("{}/users/{}", common::APP_HOST, other_user["id"]))
+        .json(&json!({ "username": "Hijacked" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn test_admin_can_update_any_profile() {
+    let client = common::get_client_with_logged_in_admin();
+    let user = common::create_test_user(&Client::new());
+
+    let response = client
+        .put(format!("{}/users/{}", common::APP_HOST, user["id"]))
+        .json(&json!({ "username": "Moderated Name" }))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let updated: Value = response.json().unwrap();
+    assert_eq!(updated["username"], "Moderated Name");
+}
+
+#[test]
+fn test_get_me() {
+    let (user, client) = common::get_logged_in_test_user();
+
+    let response = client
+        .get(format!("{}/users/me", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let me: Value = response.json().unwrap();
+    assert_eq!(me, user);
+
+    let response = Client::new()
+        .get(format!("{}/users/me", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[test]
+fn test_update_me() {
+    let (user, client) = common::get_logged_in_test_user();
+
+    let part = multipart::Part::bytes(common::load_test_image())
+        .file_name("test.image.jpg")
+        .mime_str("image/jpeg")
+        .expect("Failed to create part");
+
+    let form = multipart::Form::new()
+        .text("phone_number", "+420 111 222 333")
+        .text("description", "Glass beads")
+        .part("image", part);
+
+    let response = client
+        .put(format!("{}/users/me", common::APP_HOST))
+        .multipart(form)
+        .send()
+        .expect("Failed to send request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let updated: Value = response.json().unwrap();
+    assert_eq!(updated["id"], user["id"]);
+    assert_eq!(updated["phone_number"], "+420 111 222 333");
+    assert_eq!(updated["description"], "Glass beads");
+    assert!(updated["image_id"].is_i64());
+}
+
+#[test]
+fn test_get_my_sessions() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+    common::login(&client, &user["email"], common::TEST_PASSWORD);
+    let login = common::login(&client, &user["email"], common::TEST_PASSWORD);
+    let user_client = common::get_client_with_token(&login["token"]);
+
+    let response = user_client
+        .get(format!("{}/users/me/sessions", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let sessions: Value = response.json().unwrap();
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(
+        sessions
+            .iter()
+            .filter(|session| session["current"] == true)
+            .count(),
+        1
+    );
+    assert!(sessions[0].get("refresh_token_hash").is_none());
+}
+
+#[test]
+fn test_revoke_my_session() {
+    let client = Client::new();
+    let user = common::create_test_user(&client);
+    let stolen_login = common::login(&client, &user["email"], common::TEST_PASSWORD);
+    let stolen_client = common::get_client_with_token(&stolen_login["token"]);
+    let login = common::login(&client, &user["email"], common::TEST_PASSWORD);
+    let user_client = common::get_client_with_token(&login["token"]);
+
+    let sessions: Value = user_client
+        .get(format!("{}/users/me/sessions", common::APP_HOST))
+        .send()
+        .unwrap()
+        .json()
+        .unwrap();
+    let stolen_session = sessions
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|session| session["current"] == false)
+        .unwrap();
+
+    let response = user_client
+        .delete(format!(
+            "{}/users/me/sessions/{}",
+            common::APP_HOST,
+            stolen_session["id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = stolen_client
+        .get(format!("{}/users/me", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = user_client
+        .get(format!("{}/users/me", common::APP_HOST))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn test_revoke_other_users_session() {
+    let client = common::get_client_with_logged_in_user();
+    let other_user = common::create_test_user(&Client::new());
+    let other_login = common::login(&Client::new(), &other_user["email"], common::TEST_PASSWORD);
+    let other_client = common::get_client_with_token(&other_login["token"]);
+
+    let sessions: Value = other_client
+        .get(format!("{}/users/me/sessions", common::APP_HOST))
+        .send()
+        .unwrap()
+        .json()
+        .unwrap();
+
+    let response = client
+        .delete(format!(
+            "{}/users/me/sessions/{}",
+            common::APP_HOST,
+            sessions[0]["id"]
+        ))
+        .send()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}